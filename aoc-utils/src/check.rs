@@ -0,0 +1,84 @@
+use std::fmt::Debug;
+
+pub use rand::rngs::StdRng;
+pub use rand::Rng;
+use rand::SeedableRng;
+
+use crate::PuzzleInput;
+
+/// Number of random inputs that are checked if AOC_CHECK_CASES is not set.
+const DEFAULT_CASES: u64 = 100;
+
+/// Asserts that a reference and an optimized implementation of a solver agree on random inputs.
+///
+/// Every input is produced by `generate` from its own seed. If the implementations disagree,
+/// the panic message contains that seed, and setting AOC_CHECK_SEED to it re-runs only the
+/// failing case. AOC_CHECK_CASES changes how many inputs are checked.
+pub fn assert_agree<T, G, R, O>(mut generate: G, reference: R, optimized: O)
+where
+    T: PartialEq + Debug,
+    G: FnMut(&mut StdRng) -> String,
+    R: Fn(&PuzzleInput) -> T,
+    O: Fn(&PuzzleInput) -> T,
+{
+    for seed in seeds("AOC_CHECK_SEED", "AOC_CHECK_CASES", DEFAULT_CASES) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let input = PuzzleInput::new(generate(&mut rng));
+
+        let expected = reference(&input);
+        let actual = optimized(&input);
+        assert!(
+            expected == actual,
+            "Implementations disagree for seed {} (re-run with AOC_CHECK_SEED={}):\nreference: {:?}\noptimized: {:?}\ninput:\n{}",
+            seed,
+            seed,
+            expected,
+            actual,
+            input.raw_input
+        );
+    }
+}
+
+/// Returns the seeds of the random cases to run. A single seed can be selected with the
+/// `seed_var` env variable, the number of cases can be changed with `cases_var`.
+pub(crate) fn seeds(seed_var: &str, cases_var: &str, default_cases: u64) -> std::ops::Range<u64> {
+    let env_number = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+
+    match env_number(seed_var) {
+        Some(seed) => seed..seed + 1,
+        None => 0..env_number(cases_var).unwrap_or(default_cases),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_numbers(rng: &mut StdRng) -> String {
+        (0..rng.gen_range(1..20))
+            .map(|_| rng.gen_range(0..1000).to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn max_by_sorting(input: &PuzzleInput) -> Option<u32> {
+        let mut numbers = input.convert_to_ints_by_line::<u32>();
+        numbers.sort();
+        numbers.last().copied()
+    }
+
+    #[test]
+    fn test_agreeing_implementations() {
+        assert_agree(generate_numbers, max_by_sorting, |input| {
+            input.convert_to_ints_by_line::<u32>().into_iter().max()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Implementations disagree")]
+    fn test_disagreeing_implementations() {
+        assert_agree(generate_numbers, max_by_sorting, |input| {
+            input.convert_to_ints_by_line::<u32>().into_iter().min()
+        });
+    }
+}
@@ -0,0 +1,144 @@
+//! Cycle detection for simulations that run for many more steps than can be simulated
+//! one by one, but whose states start repeating at some point.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::hash::Hash;
+
+/// A repetition in a sequence of states: the state after `start + length` steps is the same
+/// as the state after `start` steps, so every later state repeats with period `length`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// Returns the step before the first repetition that is in the same state as step `n`.
+    pub fn equivalent_step(&self, n: usize) -> usize {
+        if n < self.start {
+            n
+        } else {
+            self.start + (n - self.start) % self.length
+        }
+    }
+
+    /// Extrapolates a quantity that grows by the same amount in every pass through the cycle,
+    /// e.g. a counter or the height of a tower, to step `n`.
+    ///
+    /// `quantity(i)` returns the quantity after `i` steps and is only called with steps up to
+    /// `start + length`, which are the steps returned by `find_cycle`.
+    pub fn extrapolate<Q>(&self, n: usize, quantity: Q) -> u64
+    where
+        Q: Fn(usize) -> u64,
+    {
+        if n < self.start {
+            return quantity(n);
+        }
+
+        let full_cycles = ((n - self.start) / self.length) as u64;
+        let growth_per_cycle = quantity(self.start + self.length) - quantity(self.start);
+        quantity(self.equivalent_step(n)) + full_cycles * growth_per_cycle
+    }
+}
+
+/// Applies `step` to `initial` until two states with the same key are found or `max_steps`
+/// steps were simulated.
+///
+/// Returns all simulated states, where the state at index `i` is the one after `i` steps,
+/// and the cycle if one was found. In that case the last state is the first repeated one.
+/// `key` extracts the part of the state that determines all following states, so that
+/// e.g. counters that keep growing can be stored in the state without preventing a cycle.
+pub fn find_cycle<S, K, G, F>(initial: S, max_steps: usize, key: G, mut step: F) -> (Vec<S>, Option<Cycle>)
+where
+    K: Hash + Eq,
+    G: Fn(&S) -> K,
+    F: FnMut(&S) -> S,
+{
+    match try_find_cycle(initial, max_steps, key, |state| Ok::<_, Infallible>(step(state))) {
+        Ok(result) => result,
+        Err(never) => match never {},
+    }
+}
+
+/// Like `find_cycle`, but for a `step` that can fail. The first error stops the search and is
+/// returned.
+pub fn try_find_cycle<S, K, G, F, E>(
+    initial: S,
+    max_steps: usize,
+    key: G,
+    mut step: F,
+) -> Result<(Vec<S>, Option<Cycle>), E>
+where
+    K: Hash + Eq,
+    G: Fn(&S) -> K,
+    F: FnMut(&S) -> Result<S, E>,
+{
+    let mut seen = HashMap::new();
+    seen.insert(key(&initial), 0);
+    let mut states = vec![initial];
+
+    for i in 1..=max_steps {
+        let state = step(&states[i - 1])?;
+        let previous = seen.insert(key(&state), i);
+        states.push(state);
+
+        if let Some(start) = previous {
+            return Ok((
+                states,
+                Some(Cycle {
+                    start,
+                    length: i - start,
+                }),
+            ));
+        }
+    }
+
+    Ok((states, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Doubles the number modulo 10 and keeps the sum of all numbers so far:
+    /// 1, 2, 4, 8, 6, 2, 4, 8, 6, ...
+    fn double(&(n, sum): &(u64, u64)) -> (u64, u64) {
+        let next = n * 2 % 10;
+        (next, sum + next)
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let (states, cycle) = find_cycle((1, 1), 100, |&(n, _)| n, double);
+        assert_eq!(cycle, Some(Cycle { start: 1, length: 4 }));
+        assert_eq!(states.len(), 6);
+        assert_eq!(cycle.unwrap().equivalent_step(1_000_001), 1);
+        assert_eq!(cycle.unwrap().equivalent_step(7), 3);
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        let (states, cycle) = find_cycle((1, 1), 100, |&(n, _)| n, double);
+        let cycle = cycle.unwrap();
+
+        let mut state = (1, 1);
+        for n in 0..50 {
+            assert_eq!(cycle.extrapolate(n, |i| states[i].1), state.1);
+            state = double(&state);
+        }
+    }
+
+    #[test]
+    fn test_no_cycle_within_max_steps() {
+        let (states, cycle) = find_cycle(0u64, 10, |&n| n, |n| n + 1);
+        assert_eq!(cycle, None);
+        assert_eq!(states.len(), 11);
+    }
+
+    #[test]
+    fn test_failing_step() {
+        let result = try_find_cycle(0u64, 10, |&n| n, |&n| if n < 3 { Ok(n + 1) } else { Err(n) });
+        assert_eq!(result, Err(3));
+    }
+}
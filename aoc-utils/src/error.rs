@@ -0,0 +1,85 @@
+use std::fmt;
+use std::num::ParseIntError;
+
+/// Error returned by a solver when the puzzle input can't be processed.
+///
+/// Errors raised while parsing carry the 1-based line number and the text of the
+/// offending input line, so malformed inputs can be diagnosed without a debugger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolveError {
+    message: String,
+    line: Option<(usize, String)>,
+}
+
+impl SolveError {
+    pub fn new<S: Into<String>>(message: S) -> SolveError {
+        SolveError {
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    /// Attaches the offending input line to this error.
+    /// An already attached line is kept, as it is the more specific one.
+    pub fn at_line(mut self, line_number: usize, line: &str) -> SolveError {
+        if self.line.is_none() {
+            self.line = Some((line_number, line.to_string()));
+        }
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line_number(&self) -> Option<usize> {
+        self.line.as_ref().map(|(number, _)| *number)
+    }
+
+    pub fn line(&self) -> Option<&str> {
+        self.line.as_ref().map(|(_, line)| line.as_str())
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.line {
+            Some((number, line)) => write!(f, "line {}: {}\n    {}", number, self.message, line),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl From<ParseIntError> for SolveError {
+    fn from(e: ParseIntError) -> Self {
+        SolveError::new(format!("invalid number: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_with_line() {
+        let error = SolveError::new("unknown instruction").at_line(3, "jmp 4");
+        assert_eq!(error.to_string(), "line 3: unknown instruction\n    jmp 4");
+        assert_eq!(error.line_number(), Some(3));
+    }
+
+    #[test]
+    fn test_keeps_innermost_line() {
+        let error = SolveError::new("bad")
+            .at_line(2, "inner")
+            .at_line(1, "outer");
+        assert_eq!(error.line(), Some("inner"));
+    }
+
+    #[test]
+    fn test_from_parse_int_error() {
+        let error: SolveError = "x1".parse::<u32>().unwrap_err().into();
+        assert_eq!(error.message(), "invalid number: invalid digit found in string");
+    }
+}
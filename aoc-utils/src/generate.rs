@@ -0,0 +1,551 @@
+//! Random puzzle input generators, one per day.
+//!
+//! Every generator takes a seeded random number generator and a size, which is the number
+//! of the day's main items (elves, rucksacks, moves, ...) as documented on each generator.
+//! The produced inputs follow the format and constraints of the real puzzle inputs.
+
+use std::collections::HashSet;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::check::StdRng;
+
+pub type Generator = fn(&mut StdRng, usize) -> String;
+
+/// Returns the input generator for the given day, if there is one.
+pub fn for_day(day: u8) -> Option<Generator> {
+    let generator: Generator = match day {
+        1 => elf_calories,
+        2 => strategy_guide,
+        3 => rucksacks,
+        4 => section_assignments,
+        5 => crate_stacks,
+        6 => datastream,
+        7 => terminal_output,
+        8 => tree_map,
+        9 => rope_motions,
+        10 => cpu_program,
+        11 => monkeys,
+        12 => heightmap,
+        13 => packet_pairs,
+        14 => rock_paths,
+        15 => sensors,
+        16 => valves,
+        _ => return None,
+    };
+
+    Some(generator)
+}
+
+fn lowercase_name(rng: &mut StdRng, length: usize) -> String {
+    (0..length).map(|_| rng.gen_range('a'..='z')).collect()
+}
+
+/// Day 1: calorie lists of `size` elves.
+pub fn elf_calories(rng: &mut StdRng, size: usize) -> String {
+    (0..size.max(1))
+        .map(|_| {
+            (0..rng.gen_range(1..=15))
+                .map(|_| rng.gen_range(1000..=60000).to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Day 2: a strategy guide with `size` rounds.
+pub fn strategy_guide(rng: &mut StdRng, size: usize) -> String {
+    (0..size.max(1))
+        .map(|_| format!("{} {}", rng.gen_range('A'..='C'), rng.gen_range('X'..='Z')))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+const ITEM_TYPES: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Day 3: `size` rucksacks, rounded up to full groups of three.
+/// Every rucksack has exactly one item type in both compartments and every group
+/// shares exactly one badge item type.
+pub fn rucksacks(rng: &mut StdRng, size: usize) -> String {
+    let mut lines = vec![];
+
+    for _ in 0..size.max(1).div_ceil(3) {
+        let mut item_types: Vec<char> = ITEM_TYPES.chars().collect();
+        item_types.shuffle(rng);
+        let badge = item_types.pop().unwrap();
+
+        // Every rucksack gets its own item types, so the badge is the only common one.
+        for pool in item_types.chunks(item_types.len() / 3).take(3) {
+            let (first_pool, second_pool) = pool.split_at(pool.len() / 2);
+            let shared = if rng.gen_bool(0.3) {
+                badge
+            } else {
+                *first_pool.choose(rng).unwrap()
+            };
+
+            let compartment_size = rng.gen_range(2..=16);
+            let mut first = vec![shared];
+            if shared != badge {
+                first.push(badge);
+            }
+            while first.len() < compartment_size {
+                first.push(*first_pool.choose(rng).unwrap());
+            }
+
+            let mut second = vec![shared];
+            while second.len() < compartment_size {
+                second.push(*second_pool.choose(rng).unwrap());
+            }
+
+            first.shuffle(rng);
+            second.shuffle(rng);
+            lines.push(first.into_iter().chain(second).collect::<String>());
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Day 4: `size` pairs of section assignments.
+pub fn section_assignments(rng: &mut StdRng, size: usize) -> String {
+    let range = |rng: &mut StdRng| {
+        let start = rng.gen_range(1..=99);
+        format!("{}-{}", start, rng.gen_range(start..=99))
+    };
+
+    (0..size.max(1))
+        .map(|_| format!("{},{}", range(rng), range(rng)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Day 5: a drawing of nine crate stacks followed by `size` valid moves.
+pub fn crate_stacks(rng: &mut StdRng, size: usize) -> String {
+    let max_height = 8.max(size / 100);
+    let mut stacks: Vec<Vec<char>> = (0..9)
+        .map(|_| {
+            (0..rng.gen_range(1..=max_height))
+                .map(|_| rng.gen_range('A'..='Z'))
+                .collect()
+        })
+        .collect();
+
+    let height = stacks.iter().map(Vec::len).max().unwrap();
+    let mut lines: Vec<String> = (0..height)
+        .rev()
+        .map(|level| {
+            stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    lines.push(
+        (1..=stacks.len())
+            .map(|i| format!(" {} ", i))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    lines.push(String::new());
+
+    for _ in 0..size.max(1) {
+        let non_empty: Vec<_> = (0..stacks.len()).filter(|i| !stacks[*i].is_empty()).collect();
+        let source = *non_empty.choose(rng).unwrap();
+        let target = (source + rng.gen_range(1..stacks.len())) % stacks.len();
+        // the real moves and day 5's parser stay below 256 crates
+        let count = rng.gen_range(1..=stacks[source].len().min(u8::MAX as usize));
+
+        let remaining = stacks[source].len() - count;
+        let moved = stacks[source].split_off(remaining);
+        stacks[target].extend(moved);
+        lines.push(format!("move {} from {} to {}", count, source + 1, target + 1));
+    }
+
+    lines.join("\n")
+}
+
+/// Day 6: a datastream with `size` characters that contains a start-of-message marker.
+pub fn datastream(rng: &mut StdRng, size: usize) -> String {
+    let size = size.max(20);
+    // a small alphabet makes markers rare, like in the real input
+    let mut stream: Vec<char> = (0..size).map(|_| rng.gen_range('a'..='f')).collect();
+
+    let mut marker: Vec<char> = ('a'..='z').collect();
+    marker.shuffle(rng);
+    let start = rng.gen_range(0..=size - 14);
+    stream[start..start + 14].copy_from_slice(&marker[..14]);
+
+    stream.into_iter().collect()
+}
+
+#[derive(Default)]
+struct GeneratedDirectory {
+    listing: Vec<String>,
+    sub_directories: Vec<(String, GeneratedDirectory)>,
+}
+
+impl GeneratedDirectory {
+    fn generate(rng: &mut StdRng, files_left: &mut usize, max_file_size: u64, depth: usize) -> Self {
+        let mut directory = GeneratedDirectory::default();
+        let mut names = HashSet::new();
+
+        for _ in 0..rng.gen_range(1..=6) {
+            let length = rng.gen_range(1..=8);
+            let name = lowercase_name(rng, length);
+            if !names.insert(name.clone()) {
+                continue;
+            }
+
+            if depth < 8 && rng.gen_bool(0.3) {
+                let sub_directory = Self::generate(rng, files_left, max_file_size, depth + 1);
+                directory.add_sub_directory(name, sub_directory);
+            } else if *files_left > 0 {
+                *files_left -= 1;
+                let name = if rng.gen_bool(0.5) {
+                    format!("{}.{}", name, lowercase_name(rng, 3))
+                } else {
+                    name
+                };
+                let size = rng.gen_range(max_file_size * 3 / 5..=max_file_size).max(1);
+                directory.listing.push(format!("{} {}", size, name));
+            }
+        }
+
+        directory
+    }
+
+    fn add_sub_directory(&mut self, name: String, directory: GeneratedDirectory) {
+        self.listing.push(format!("dir {}", name));
+        self.sub_directories.push((name, directory));
+    }
+
+    fn write_transcript(&self, lines: &mut Vec<String>) {
+        lines.push("$ ls".to_string());
+        lines.extend(self.listing.iter().cloned());
+
+        for (name, directory) in &self.sub_directories {
+            lines.push(format!("$ cd {}", name));
+            directory.write_transcript(lines);
+            lines.push("$ cd ..".to_string());
+        }
+    }
+}
+
+/// Day 7: a terminal transcript exploring a file system with `size` files. File sizes are
+/// chosen so that the files fit on the 70000000 byte disk, which holds for up to that many
+/// files, and with enough files leave less than the 30000000 bytes free that the update needs.
+pub fn terminal_output(rng: &mut StdRng, size: usize) -> String {
+    let mut files_left = size.max(1);
+    let max_file_size = (69_999_999 / files_left as u64).clamp(1, 300_000);
+    let mut root = GeneratedDirectory::generate(rng, &mut files_left, max_file_size, 0);
+    while files_left > 0 {
+        // the digit keeps these names distinct from the generated lowercase names
+        let name = format!("d{}", root.sub_directories.len());
+        let directory = GeneratedDirectory::generate(rng, &mut files_left, max_file_size, 1);
+        root.add_sub_directory(name, directory);
+    }
+
+    let mut lines = vec!["$ cd /".to_string()];
+    root.write_transcript(&mut lines);
+    lines.join("\n")
+}
+
+/// Day 8: a square tree map with `size` trees per side.
+pub fn tree_map(rng: &mut StdRng, size: usize) -> String {
+    let size = size.max(1);
+    (0..size)
+        .map(|_| (0..size).map(|_| rng.gen_range('0'..='9')).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Day 9: `size` head motions.
+pub fn rope_motions(rng: &mut StdRng, size: usize) -> String {
+    (0..size.max(1))
+        .map(|_| {
+            let direction = ['U', 'D', 'L', 'R'].choose(rng).unwrap();
+            format!("{} {}", direction, rng.gen_range(1..=20))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Day 10: a program with `size` instructions.
+/// Note that the CRT only has 240 pixels, programs running longer aren't fully drawn.
+pub fn cpu_program(rng: &mut StdRng, size: usize) -> String {
+    (0..size.max(1))
+        .map(|_| {
+            if rng.gen_bool(0.3) {
+                "noop".to_string()
+            } else {
+                format!("addx {}", rng.gen_range(-20..=20))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Day 11: monkeys holding `size` items in total.
+/// At most eight monkeys are generated, because the product of their divisors has to stay
+/// small enough that squaring a worry level doesn't overflow.
+pub fn monkeys(rng: &mut StdRng, size: usize) -> String {
+    const DIVISORS: [u64; 8] = [2, 3, 5, 7, 11, 13, 17, 19];
+
+    let count = (size / 4).clamp(2, DIVISORS.len());
+    let mut divisors = DIVISORS;
+    divisors.shuffle(rng);
+
+    let mut items = vec![vec![]; count];
+    for _ in 0..size {
+        items[rng.gen_range(0..count)].push(rng.gen_range(50..100).to_string());
+    }
+
+    (0..count)
+        .map(|i| {
+            let operation = match rng.gen_range(0..3) {
+                0 => format!("* {}", rng.gen_range(2..20)),
+                1 => format!("+ {}", rng.gen_range(1..10)),
+                _ => "* old".to_string(),
+            };
+            let mut other_monkey = || (i + rng.gen_range(1..count)) % count;
+
+            format!(
+                "Monkey {}:
+  Starting items: {}
+  Operation: new = old {}
+  Test: divisible by {}
+    If true: throw to monkey {}
+    If false: throw to monkey {}",
+                i,
+                items[i].join(", "),
+                operation,
+                divisors[i],
+                other_monkey(),
+                other_monkey()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Day 12: a heightmap with `size` columns and rows.
+/// A path from the start to the best signal position is guaranteed to exist.
+pub fn heightmap(rng: &mut StdRng, size: usize) -> String {
+    let size = size.max(6);
+    let mut map: Vec<Vec<char>> = (0..size)
+        .map(|_| (0..size).map(|_| rng.gen_range('a'..='z')).collect())
+        .collect();
+
+    // The path snakes through the rows and climbs evenly up to z.
+    let length = rng.gen_range(28..=size * size);
+    for k in 0..length {
+        let y = k / size;
+        let x = if y.is_multiple_of(2) { k % size } else { size - 1 - k % size };
+        map[y][x] = if k == 0 {
+            'S'
+        } else if k == length - 1 {
+            'E'
+        } else {
+            (b'a' + (k * 25 / (length - 2)).min(25) as u8) as char
+        };
+    }
+
+    map.into_iter()
+        .map(|row| row.into_iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn packet(rng: &mut StdRng, depth: usize) -> String {
+    let values: Vec<_> = (0..rng.gen_range(0..5))
+        .map(|_| {
+            if depth == 0 || rng.gen_bool(0.6) {
+                rng.gen_range(0..=10).to_string()
+            } else {
+                packet(rng, depth - 1)
+            }
+        })
+        .collect();
+
+    format!("[{}]", values.join(","))
+}
+
+/// Day 13: `size` pairs of packets. Some pairs consist of two equal packets.
+pub fn packet_pairs(rng: &mut StdRng, size: usize) -> String {
+    (0..size.max(1))
+        .map(|_| {
+            let left = packet(rng, 4);
+            let right = if rng.gen_bool(0.1) {
+                left.clone()
+            } else {
+                packet(rng, 4)
+            };
+            format!("{}\n{}", left, right)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Day 14: `size` paths of rock below the sand source.
+pub fn rock_paths(rng: &mut StdRng, size: usize) -> String {
+    (0..size.max(1))
+        .map(|_| {
+            let (mut x, mut y): (i32, i32) = (rng.gen_range(440..=560), rng.gen_range(5..=150));
+            let mut points = vec![format!("{},{}", x, y)];
+
+            for i in 0..rng.gen_range(1..=5) {
+                let distance = rng.gen_range(1..=8);
+                if i % 2 == 0 {
+                    x = (x + if rng.gen_bool(0.5) { distance } else { -distance }).clamp(400, 600);
+                } else {
+                    y = (y + if rng.gen_bool(0.5) { distance } else { -distance }).clamp(5, 160);
+                }
+                points.push(format!("{},{}", x, y));
+            }
+
+            points.join(" -> ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Day 15: `size` sensors around the 4000000x4000000 search area.
+/// The sensors never cover the randomly placed distress beacon, but they might
+/// leave other positions uncovered as well.
+pub fn sensors(rng: &mut StdRng, size: usize) -> String {
+    let distress_beacon = (rng.gen_range(0..=4_000_000i64), rng.gen_range(0..=4_000_000i64));
+
+    (0..size.max(1))
+        .map(|_| {
+            let sensor = loop {
+                let sensor = (rng.gen_range(0..=4_000_000i64), rng.gen_range(0..=4_000_000i64));
+                if sensor != distress_beacon {
+                    break sensor;
+                }
+            };
+            let max_distance = (sensor.0 - distress_beacon.0).abs()
+                + (sensor.1 - distress_beacon.1).abs()
+                - 1;
+            let distance = rng.gen_range(0..=max_distance);
+            let dx = rng.gen_range(0..=distance);
+            let dy = distance - dx;
+            let beacon = (
+                sensor.0 + if rng.gen_bool(0.5) { dx } else { -dx },
+                sensor.1 + if rng.gen_bool(0.5) { dy } else { -dy },
+            );
+
+            format!(
+                "Sensor at x={}, y={}: closest beacon is at x={}, y={}",
+                sensor.0, sensor.1, beacon.0, beacon.1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Day 16: a connected tunnel network of `size` valves, starting at valve AA.
+/// At most 64 valves are generated and only up to 15 of them have a flow rate.
+pub fn valves(rng: &mut StdRng, size: usize) -> String {
+    let count = size.clamp(2, 64);
+
+    let mut names = vec!["AA".to_string()];
+    while names.len() < count {
+        let name: String = (0..2).map(|_| rng.gen_range('A'..='Z')).collect();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    // a random spanning tree keeps the network connected
+    let mut tunnels = vec![vec![]; count];
+    for i in 1..count {
+        let j = rng.gen_range(0..i);
+        tunnels[i].push(j);
+        tunnels[j].push(i);
+    }
+    for _ in 0..count / 3 {
+        let (i, j) = (rng.gen_range(0..count), rng.gen_range(0..count));
+        if i != j && !tunnels[i].contains(&j) {
+            tunnels[i].push(j);
+            tunnels[j].push(i);
+        }
+    }
+
+    let mut flow_valves: Vec<_> = (1..count).collect();
+    flow_valves.shuffle(rng);
+    flow_valves.truncate(15.min(count / 2).max(1));
+
+    (0..count)
+        .map(|i| {
+            let flow_rate = if flow_valves.contains(&i) {
+                rng.gen_range(1..=25)
+            } else {
+                0
+            };
+            let targets: Vec<_> = tunnels[i].iter().map(|j| names[*j].as_str()).collect();
+            let tunnel_text = if targets.len() == 1 {
+                "tunnel leads to valve"
+            } else {
+                "tunnels lead to valves"
+            };
+
+            format!(
+                "Valve {} has flow rate={}; {} {}",
+                names[i],
+                flow_rate,
+                tunnel_text,
+                targets.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_input() {
+        for day in 1..=16 {
+            let generator = for_day(day).unwrap();
+            let first = generator(&mut StdRng::seed_from_u64(7), 20);
+            let second = generator(&mut StdRng::seed_from_u64(7), 20);
+            assert_eq!(first, second, "day {}", day);
+        }
+    }
+
+    #[test]
+    fn test_rucksacks_share_one_badge() {
+        let input = rucksacks(&mut StdRng::seed_from_u64(1), 30);
+        let lines: Vec<_> = input.lines().collect();
+        assert_eq!(lines.len(), 30);
+
+        for group in lines.chunks(3) {
+            let common: Vec<_> = group[0]
+                .chars()
+                .filter(|c| group[1].contains(*c) && group[2].contains(*c))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            assert_eq!(common.len(), 1, "{:?}", group);
+        }
+    }
+
+    #[test]
+    fn test_datastream_contains_marker() {
+        let stream = datastream(&mut StdRng::seed_from_u64(3), 100);
+        let chars: Vec<_> = stream.chars().collect();
+        assert!(chars
+            .windows(14)
+            .any(|w| w.iter().collect::<HashSet<_>>().len() == 14));
+    }
+}
@@ -0,0 +1,139 @@
+//! Closed integer intervals, e.g. ranges of sections or the cells of a row covered by a sensor.
+
+use std::fmt;
+
+/// The integers from `start` to `end`, both included. An interval always contains at least
+/// one integer, operations that could produce an empty interval return an `Option` instead.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Interval {
+    /// Creates the interval from `start` to `end`, or `None` if `end` is before `start`.
+    pub fn new(start: i64, end: i64) -> Option<Interval> {
+        if start <= end {
+            Some(Interval { start, end })
+        } else {
+            None
+        }
+    }
+
+    /// Number of integers in the interval.
+    pub fn len(&self) -> u64 {
+        self.start.abs_diff(self.end) + 1
+    }
+
+    /// Always false, an interval contains at least one integer.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn contains_point(&self, point: i64) -> bool {
+        self.start <= point && point <= self.end
+    }
+
+    /// Returns true if `other` lies completely within this interval.
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// Returns true if both intervals have at least one integer in common.
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Returns the integers that are in both intervals.
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        Interval::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// Returns the interval covering exactly the integers of both intervals. This is only
+    /// possible if they overlap or are adjacent, otherwise there would be a gap.
+    pub fn union(&self, other: &Interval) -> Option<Interval> {
+        let adjacent = self.end.checked_add(1) == Some(other.start)
+            || other.end.checked_add(1) == Some(self.start);
+
+        if self.overlaps(other) || adjacent {
+            Interval::new(self.start.min(other.start), self.end.max(other.end))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Merges overlapping and adjacent intervals. The returned intervals are sorted and
+/// separated by gaps, so they cover every integer at most once.
+pub fn merge<I>(intervals: I) -> Vec<Interval>
+where
+    I: IntoIterator<Item = Interval>,
+{
+    let mut intervals: Vec<Interval> = intervals.into_iter().collect();
+    intervals.sort();
+
+    let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        match merged.last_mut().and_then(|last| last.union(&interval)) {
+            Some(union) => *merged.last_mut().unwrap() = union,
+            None => merged.push(interval),
+        }
+    }
+
+    merged
+}
+
+/// Returns how many integers are in at least one of the intervals.
+pub fn covered_length<I>(intervals: I) -> u64
+where
+    I: IntoIterator<Item = Interval>,
+{
+    merge(intervals).iter().map(Interval::len).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(start: i64, end: i64) -> Interval {
+        Interval::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_interval_operations() {
+        let a = interval(2, 8);
+        let b = interval(3, 7);
+        let c = interval(9, 12);
+
+        assert_eq!(a.len(), 7);
+        assert!(a.contains(&b) && !b.contains(&a));
+        assert!(a.overlaps(&b) && !a.overlaps(&c));
+        assert_eq!(a.intersection(&b), Some(b));
+        assert_eq!(a.intersection(&c), None);
+        assert_eq!(a.union(&c), Some(interval(2, 12)));
+        assert_eq!(b.union(&c), None);
+        assert_eq!(Interval::new(3, 2), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let intervals = [
+            interval(5, 7),
+            interval(-3, 1),
+            interval(0, 2),
+            interval(4, 4),
+            interval(10, 11),
+        ];
+        assert_eq!(
+            merge(intervals),
+            vec![interval(-3, 2), interval(4, 7), interval(10, 11)]
+        );
+        assert_eq!(covered_length(intervals), 6 + 4 + 2);
+    }
+}
@@ -1,6 +1,17 @@
-use std::fs;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::BufReader;
 use std::iter::Map;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+pub mod check;
+pub mod cycle;
+mod error;
+pub mod generate;
+pub mod interval;
+pub mod smoke;
+
+pub use error::SolveError;
 
 pub struct PuzzleInput {
     pub raw_input: String,
@@ -14,35 +25,62 @@ impl PuzzleInput {
     }
 
     /// Reads the puzzle input for the puzzle with the given day from input_day<day_number>.txt
+    /// If the AOC_INPUT environment variable is set, the input is read from that file instead,
+    /// e.g. to run a solution against a generated input.
     pub fn get_input(day: u8) -> PuzzleInput {
-        let path_string = format!("input_day{:02}.txt", day);
-        let path = Path::new(&path_string);
-
-        // When running in tests, the working directory is inside the package, but
-        // when running the actual puzzle, it's in the workspace root.
-        let outside_path_str = format!("day{:02}/{}", day, path.display());
-        let outside_path = Path::new(&outside_path_str);
-
-        if path.exists() {
-            let content = fs::read_to_string(path).expect("Unable to read file");
-            PuzzleInput::new(content)
-        } else if outside_path.exists() {
-            let content = fs::read_to_string(outside_path).expect("Unable to read file");
-            PuzzleInput::new(content)
-        } else {
-            let cwd = std::env::current_dir().unwrap();
-            panic!(
-                "Puzzle input at {}/{} does not exist",
-                cwd.display(),
-                path.display()
-            );
-        }
+        let content = fs::read_to_string(input_path(day)).expect("Unable to read file");
+        PuzzleInput::new(content)
     }
 
-    pub fn lines(&self) -> Map<std::str::Lines, fn(&str) -> String> {
+    /// Opens the same file as `get_input` for reading it line by line, for solutions that
+    /// stream their input instead of holding all of it in memory.
+    pub fn open_input(day: u8) -> BufReader<File> {
+        BufReader::new(File::open(input_path(day)).expect("Unable to open file"))
+    }
+
+    pub fn lines(&self) -> Map<std::str::Lines<'_>, fn(&str) -> String> {
         self.raw_input.lines().map(|s| s.to_string())
     }
 
+    /// Iterates over the lines of the input together with their 1-based line number.
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.raw_input.lines().enumerate().map(|(i, l)| (i + 1, l))
+    }
+
+    /// Parses every line using the given function. Errors are annotated with the offending line.
+    pub fn parse_lines<T, F>(&self, mut parse: F) -> Result<Vec<T>, SolveError>
+    where
+        F: FnMut(&str) -> Result<T, SolveError>,
+    {
+        self.numbered_lines()
+            .map(|(number, line)| parse(line).map_err(|e| e.at_line(number, line)))
+            .collect()
+    }
+
+    /// Splits the input into blocks of lines that are separated by empty lines.
+    /// Each line is returned with its 1-based line number.
+    pub fn blocks(&self) -> Vec<Vec<(usize, &str)>> {
+        let mut blocks = vec![];
+        let mut current = vec![];
+
+        for (number, line) in self.numbered_lines() {
+            if line.is_empty() {
+                if !current.is_empty() {
+                    blocks.push(current);
+                    current = vec![];
+                }
+            } else {
+                current.push((number, line));
+            }
+        }
+
+        if !current.is_empty() {
+            blocks.push(current);
+        }
+
+        blocks
+    }
+
     pub fn convert_to_ints_by_line<S: std::str::FromStr>(&self) -> Vec<S> {
         self.lines()
             .filter_map(|s| s.parse::<S>().ok())
@@ -58,8 +96,71 @@ impl PuzzleInput {
     }
 }
 
+/// Returns the path of the input file of the given day, see `PuzzleInput::get_input`.
+fn input_path(day: u8) -> PathBuf {
+    if let Ok(path) = std::env::var("AOC_INPUT") {
+        return PathBuf::from(path);
+    }
+
+    let path = PathBuf::from(format!("input_day{:02}.txt", day));
+
+    // When running in tests, the working directory is inside the package, but
+    // when running the actual puzzle, it's in the workspace root.
+    let outside_path = Path::new(&format!("day{:02}", day)).join(&path);
+
+    if path.exists() {
+        path
+    } else if outside_path.exists() {
+        outside_path
+    } else {
+        let cwd = std::env::current_dir().unwrap();
+        panic!(
+            "Puzzle input at {}/{} does not exist",
+            cwd.display(),
+            path.display()
+        );
+    }
+}
+
+/// Prints the answer of one puzzle part. If the solver failed, the error is printed
+/// instead and the process exits with a non-zero exit code.
+pub fn print_answer<T: Display>(part: &str, answer: Result<T, SolveError>) {
+    match answer {
+        Ok(answer) => println!("{}: {}", part, answer),
+        Err(e) => {
+            eprintln!("{}: error: {}", part, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the value of a successful result. Otherwise the error is printed and the process
+/// exits with a non-zero exit code like in `print_answer`, for the extra command line modes
+/// of the days.
+pub fn exit_on_error<T, E: Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Writes every frame of a trace to its own file in the given directory, named by its step.
+pub fn write_frames(frames: &[String], directory: &Path) -> Result<(), SolveError> {
+    let write = || -> std::io::Result<()> {
+        fs::create_dir_all(directory)?;
+        for (step, frame) in frames.iter().enumerate() {
+            fs::write(directory.join(format!("frame_{:05}.txt", step)), frame)?;
+        }
+        Ok(())
+    };
+
+    write().map_err(|e| SolveError::new(format!("Unable to write frames: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     // Should be able to get input "Hello, this is a test" from day 0
     #[test]
     fn test_get_input_0_success() {
@@ -73,4 +174,24 @@ mod tests {
     fn test_get_input_1b_fail() {
         super::PuzzleInput::get_input(1);
     }
+
+    #[test]
+    fn test_parse_lines_reports_line() {
+        let input = PuzzleInput::new("1\n2\nthree\n4");
+        let error = input
+            .parse_lines(|l| l.parse::<u32>().map_err(SolveError::from))
+            .unwrap_err();
+        assert_eq!(error.line_number(), Some(3));
+        assert_eq!(error.line(), Some("three"));
+    }
+
+    #[test]
+    fn test_blocks() {
+        let input = PuzzleInput::new("a\nb\n\nc\n\n\nd\n");
+        let blocks = input.blocks();
+        assert_eq!(
+            blocks,
+            vec![vec![(1, "a"), (2, "b")], vec![(4, "c")], vec![(7, "d")]]
+        );
+    }
 }
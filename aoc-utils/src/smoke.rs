@@ -0,0 +1,194 @@
+//! Randomized smoke tests that run the solvers of a day on mutated inputs.
+//!
+//! This is not coverage guided fuzzing like cargo-fuzz: a fixed number of inputs is derived
+//! from a corpus of valid inputs (the day's test input and generated inputs) by random byte
+//! level mutations, and every input is solved through `PuzzleInput::new` like a real puzzle
+//! input. Solvers are expected to reject malformed inputs with an error; panics, including
+//! arithmetic overflows in debug builds, make the smoke test fail. Stack overflows abort the
+//! whole test process instead.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::check::{seeds, StdRng};
+use crate::{generate, PuzzleInput, SolveError};
+
+/// Number of inputs that are tried if AOC_SMOKE_ITERATIONS is not set.
+const DEFAULT_ITERATIONS: u64 = 1000;
+
+/// Tokens that are likely to hit edge cases in the parsers.
+const INTERESTING_TOKENS: &[&str] = &[
+    "\n", "\n\n", " ", ",", ", ", "-", "[", "]", "[]", "$ ", "->", ":", "=", "old", "é",
+];
+
+/// Numbers that are likely to hit edge cases. Most of them are extremes that still fit into the
+/// common integer types, to catch overflows in the solvers and not only in the parsers.
+const INTERESTING_NUMBERS: &[&str] = &[
+    "0", "1", "-1", "127", "255", "65535", "2147483647", "2147483648", "4294967295",
+    "4294967296", "1000000000000000000", "9223372036854775807", "18446744073709551615",
+    "18446744073709551616",
+];
+
+/// Solves random inputs with both solvers of a day and asserts that they never panic.
+///
+/// If an input makes a solver panic, the panic message contains the input and its seed.
+/// Setting AOC_SMOKE_SEED to that seed re-runs only the failing input, AOC_SMOKE_ITERATIONS
+/// changes how many inputs are tried.
+pub fn smoke_test<A, B, SA, SB>(day: u8, corpus: &[&str], solve_a: SA, solve_b: SB)
+where
+    SA: Fn(&PuzzleInput) -> Result<A, SolveError>,
+    SB: Fn(&PuzzleInput) -> Result<B, SolveError>,
+{
+    run(day, corpus, None, solve_a, solve_b);
+}
+
+/// Like [`smoke_test`], but skips inputs that contain a number larger than `max_number`.
+///
+/// This is meant for days whose running time grows with the numbers in the input, like the
+/// distances of the rope motions, where a valid input with huge numbers would never finish.
+pub fn smoke_test_bounded<A, B, SA, SB>(
+    day: u8,
+    corpus: &[&str],
+    max_number: u64,
+    solve_a: SA,
+    solve_b: SB,
+) where
+    SA: Fn(&PuzzleInput) -> Result<A, SolveError>,
+    SB: Fn(&PuzzleInput) -> Result<B, SolveError>,
+{
+    run(day, corpus, Some(max_number), solve_a, solve_b);
+}
+
+fn run<A, B, SA, SB>(day: u8, corpus: &[&str], max_number: Option<u64>, solve_a: SA, solve_b: SB)
+where
+    SA: Fn(&PuzzleInput) -> Result<A, SolveError>,
+    SB: Fn(&PuzzleInput) -> Result<B, SolveError>,
+{
+    let mut corpus: Vec<String> = corpus.iter().map(|s| s.to_string()).collect();
+    if let Some(generator) = generate::for_day(day) {
+        for seed in 0..5 {
+            corpus.push(generator(&mut StdRng::seed_from_u64(seed), seed as usize + 1));
+        }
+    }
+
+    for seed in seeds("AOC_SMOKE_SEED", "AOC_SMOKE_ITERATIONS", DEFAULT_ITERATIONS) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let bytes = if corpus.is_empty() || rng.gen_bool(0.1) {
+            let length = rng.gen_range(0..64);
+            (0..length).map(|_| rng.gen()).collect()
+        } else {
+            let original = corpus.choose(&mut rng).unwrap();
+            mutate(&mut rng, original.as_bytes())
+        };
+
+        let input = PuzzleInput::new(String::from_utf8_lossy(&bytes));
+        if max_number.is_some_and(|max| largest_number(&input.raw_input) > max as u128) {
+            continue;
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = solve_a(&input);
+            let _ = solve_b(&input);
+        }));
+
+        assert!(
+            result.is_ok(),
+            "Solver panicked for seed {} (re-run with AOC_SMOKE_SEED={}) on input:\n{:?}",
+            seed,
+            seed,
+            input.raw_input
+        );
+    }
+}
+
+/// Returns the largest number in the input, or 0 if it contains none. Numbers that don't fit
+/// into u64 count as u64::MAX + 1.
+fn largest_number(input: &str) -> u128 {
+    input
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|digits| !digits.is_empty())
+        .map(|digits| digits.parse::<u64>().map_or(u64::MAX as u128 + 1, |n| n as u128))
+        .max()
+        .unwrap_or(0)
+}
+
+fn mutate(rng: &mut StdRng, input: &[u8]) -> Vec<u8> {
+    let mut bytes = input.to_vec();
+
+    for _ in 0..rng.gen_range(1..=8) {
+        let position = rng.gen_range(0..=bytes.len());
+        match rng.gen_range(0..6) {
+            0 if position < bytes.len() => bytes[position] = rng.gen(),
+            1 => {
+                let tokens = if rng.gen() { INTERESTING_TOKENS } else { INTERESTING_NUMBERS };
+                let token = tokens.choose(rng).unwrap();
+                bytes.splice(position..position, token.bytes());
+            }
+            2 => {
+                let end = rng.gen_range(position..=bytes.len().min(position + 16));
+                bytes.drain(position..end);
+            }
+            3 => {
+                let end = rng.gen_range(position..=bytes.len().min(position + 16));
+                let copy = bytes[position..end].to_vec();
+                let target = rng.gen_range(0..=bytes.len());
+                bytes.splice(target..target, copy);
+            }
+            4 => {
+                // replace the next number as a whole, inserting a number next to one would
+                // mostly create numbers that don't even parse
+                let start = match bytes[position..].iter().position(u8::is_ascii_digit) {
+                    Some(offset) => position + offset,
+                    None => continue,
+                };
+                let length = bytes[start..].iter().take_while(|b| b.is_ascii_digit()).count();
+                let number = INTERESTING_NUMBERS.choose(rng).unwrap();
+                bytes.splice(start..start + length, number.bytes());
+            }
+            _ if position < bytes.len() => bytes[position] = rng.gen_range(b'0'..=b'9'),
+            _ => bytes.push(b'\n'),
+        }
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_numbers(input: &PuzzleInput) -> Result<Vec<u32>, SolveError> {
+        input.parse_lines(|l| Ok(l.parse::<u32>()?))
+    }
+
+    #[test]
+    fn test_smoke_test_accepts_errors() {
+        smoke_test(0, &["1\n2\n3"], parse_numbers, parse_numbers);
+    }
+
+    #[test]
+    fn test_smoke_test_bounded_skips_large_numbers() {
+        smoke_test_bounded(0, &["1\n2\n3"], 1000, parse_numbers, |input| {
+            let numbers = parse_numbers(input)?;
+            assert!(numbers.iter().all(|&n| n <= 1000));
+            Ok(numbers)
+        });
+    }
+
+    #[test]
+    fn test_largest_number() {
+        assert_eq!(largest_number("R 4\nU 12"), 12);
+        assert_eq!(largest_number("no numbers"), 0);
+        assert_eq!(largest_number("18446744073709551616"), u64::MAX as u128 + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Solver panicked")]
+    fn test_smoke_test_finds_panics() {
+        smoke_test(0, &["1\n2\n3"], parse_numbers, |input| {
+            input.parse_lines(|l| Ok(l.parse::<u32>().unwrap()))
+        });
+    }
+}
@@ -1,18 +1,18 @@
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 0;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    input.lines().count()
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    Ok(input.lines().count())
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    input.lines().count()
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    Ok(input.lines().count())
 }
 
 #[cfg(test)]
@@ -24,17 +24,17 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 0);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 0);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 0);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 0);
     }
 }
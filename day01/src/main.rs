@@ -1,40 +1,231 @@
-use aoc_utils::PuzzleInput;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::BufRead;
+
+use aoc_utils::{exit_on_error, print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 1;
 
 fn main() {
-    let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let output = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            let input = PuzzleInput::get_input(DAY);
+            print_answer("A", solve_a(&input));
+            print_answer("B", solve_b(&input));
+            return;
+        }
+        ["--top", k] => {
+            let k = k.parse().unwrap_or_else(|_| usage());
+            let elves = ElfReader::new(PuzzleInput::open_input(DAY));
+            top_elves(elves, k).map(|top| {
+                top.iter()
+                    .map(|elf| format!("Elf {}: {}\n", elf.index, elf.calories))
+                    .collect()
+            })
+        }
+        ["--report"] => report(&PuzzleInput::get_input(DAY)),
+        ["--csv"] => export_csv(&PuzzleInput::get_input(DAY)),
+        _ => usage(),
+    };
+
+    print!("{}", exit_on_error(output));
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: day01 [--top <k> | --report | --csv]");
+    std::process::exit(2);
+}
+
+#[derive(Debug, PartialEq)]
+struct Elf {
+    /// 1-based position of the elf in the input
+    index: usize,
+    items: usize,
+    calories: u32,
+}
+
+/// Reads the elves from the input one at a time, so that inputs of any size can be processed.
+struct ElfReader<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line_number: usize,
+    elf_count: usize,
+}
+
+impl<R: BufRead> ElfReader<R> {
+    fn new(reader: R) -> Self {
+        ElfReader {
+            lines: reader.lines(),
+            line_number: 0,
+            elf_count: 0,
+        }
+    }
 }
 
-fn create_snack_count_list(input: &PuzzleInput) -> Vec<u32> {
-    input
-        .raw_input
-        .split("\n\n")
-        .map(|elf_lines| {
-            elf_lines
-                .split('\n')
-                .filter(|line| !line.is_empty())
-                .map(|line| 
-                    line.parse::<u32>().unwrap()
-        )
-                .sum::<u32>()
+impl<R: BufRead> Iterator for ElfReader<R> {
+    type Item = Result<Elf, SolveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut total: Option<u32> = None;
+        let mut items = 0;
+
+        for line in self.lines.by_ref() {
+            self.line_number += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(SolveError::new(format!("Unable to read input: {}", e)))),
+            };
+
+            if line.is_empty() {
+                if total.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            let sum = line
+                .parse::<u32>()
+                .map_err(SolveError::from)
+                .and_then(|calories| {
+                    total
+                        .unwrap_or(0)
+                        .checked_add(calories)
+                        .ok_or_else(|| SolveError::new("Calorie sum is too large"))
+                });
+            match sum {
+                Ok(sum) => {
+                    total = Some(sum);
+                    items += 1;
+                }
+                Err(e) => return Some(Err(e.at_line(self.line_number, &line))),
+            }
+        }
+
+        total.map(|total| {
+            self.elf_count += 1;
+            Ok(Elf {
+                index: self.elf_count,
+                items,
+                calories: total,
+            })
         })
+    }
+}
+
+fn create_snack_count_list(input: &PuzzleInput) -> Result<Vec<Elf>, SolveError> {
+    ElfReader::new(input.raw_input.as_bytes()).collect()
+}
+
+/// Returns the `k` elves carrying the most calories, most calories first.
+/// Only the current top `k` elves are kept in memory while reading the elves.
+fn top_elves<I>(elves: I, k: usize) -> Result<Vec<Elf>, SolveError>
+where
+    I: Iterator<Item = Result<Elf, SolveError>>,
+{
+    // Min-heap of the best elves so far, the weakest of them can be replaced on top
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+    for elf in elves {
+        let Elf { index, items, calories } = elf?;
+        heap.push(Reverse((calories, index, items)));
+        if heap.len() > k {
+            heap.pop();
+        }
+    }
+
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((calories, index, items))| Elf { index, items, calories })
+        .collect())
+}
+
+/// Percentiles of the calories that are shown in the report.
+const REPORT_PERCENTILES: [u32; 5] = [10, 25, 75, 90, 99];
+const HISTOGRAM_BUCKETS: usize = 10;
+const HISTOGRAM_WIDTH: usize = 50;
+
+/// Returns the `p`-th percentile of the sorted values, interpolating between the closest ranks.
+fn percentile(sorted: &[u32], p: u32) -> f64 {
+    let rank = (sorted.len() - 1) as f64 * p as f64 / 100.0;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    let fraction = rank - lower as f64;
+    sorted[lower] as f64 + (sorted[upper] as f64 - sorted[lower] as f64) * fraction
+}
+
+/// Splits the range of the sorted values into at most `buckets` equally wide buckets and counts
+/// the values in each of them. Returns the lowest value of each bucket with its count.
+fn histogram(sorted: &[u32], buckets: usize) -> Vec<(u32, usize)> {
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let width = (max - min) / buckets as u32 + 1;
+
+    let mut counts = vec![0; ((max - min) / width) as usize + 1];
+    for value in sorted {
+        counts[((value - min) / width) as usize] += 1;
+    }
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| (min + i as u32 * width, count))
         .collect()
 }
 
-fn solve_a(input: &PuzzleInput) -> u32 {
-*create_snack_count_list(input)
-        .iter()
-        .max()
-        .unwrap()
+/// Lists the calories and items of every elf followed by statistics over all elves.
+fn report(input: &PuzzleInput) -> Result<String, SolveError> {
+    let elves = create_snack_count_list(input)?;
+    if elves.is_empty() {
+        return Err(SolveError::new("Input contains no elves"));
+    }
+
+    let mut sorted: Vec<u32> = elves.iter().map(|elf| elf.calories).collect();
+    sorted.sort();
+    let total: u64 = sorted.iter().map(|&c| c as u64).sum();
+
+    let mut report = String::from("  Elf  Items  Calories\n");
+    for elf in &elves {
+        report += &format!("{:>5}  {:>5}  {:>8}\n", elf.index, elf.items, elf.calories);
+    }
+
+    report += &format!("\nElves: {}\n", elves.len());
+    report += &format!("Total: {}\n", total);
+    report += &format!("Mean: {:.1}\n", total as f64 / elves.len() as f64);
+    report += &format!("Median: {:.1}\n", percentile(&sorted, 50));
+    report += &format!("Min: {}\nMax: {}\n", sorted[0], sorted[sorted.len() - 1]);
+    for p in REPORT_PERCENTILES {
+        report += &format!("P{}: {:.1}\n", p, percentile(&sorted, p));
+    }
+
+    let buckets = histogram(&sorted, HISTOGRAM_BUCKETS);
+    let largest = buckets.iter().map(|(_, count)| *count).max().unwrap();
+    report += "\nHistogram:\n";
+    for (start, count) in buckets {
+        let bar = "#".repeat(count * HISTOGRAM_WIDTH / largest);
+        report += &format!("{:>8}  {:>5}  {}\n", start, count, bar);
+    }
+
+    Ok(report)
+}
+
+/// Exports the index, number of items and calories of every elf as CSV.
+fn export_csv(input: &PuzzleInput) -> Result<String, SolveError> {
+    let mut csv = String::from("elf,items,calories\n");
+    for elf in create_snack_count_list(input)? {
+        csv += &format!("{},{},{}\n", elf.index, elf.items, elf.calories);
+    }
+    Ok(csv)
 }
 
-fn solve_b(input: &PuzzleInput) -> u32 {
-    let mut list = create_snack_count_list(input);
-    list.sort();
-    list.reverse(); // descending order
-    list.iter().take(3).sum::<u32>()
+fn solve_a(input: &PuzzleInput) -> Result<u32, SolveError> {
+    top_elves(ElfReader::new(input.raw_input.as_bytes()), 1)?
+        .first()
+        .map(|elf| elf.calories)
+        .ok_or_else(|| SolveError::new("Input contains no elves"))
+}
+
+fn solve_b(input: &PuzzleInput) -> Result<u32, SolveError> {
+    top_elves(ElfReader::new(input.raw_input.as_bytes()), 3)?
+        .iter()
+        .try_fold(0u32, |sum, elf| sum.checked_add(elf.calories))
+        .ok_or_else(|| SolveError::new("Calorie sum is too large"))
 }
 
 #[cfg(test)]
@@ -59,17 +250,42 @@ mod tests {
  #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 24000);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 24000);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 45000);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 45000);
+    }
+
+    #[test]
+    fn test_top_elves() {
+        let elves = ElfReader::new(TEST_INPUT.as_bytes());
+        let top: Vec<_> = top_elves(elves, 2)
+            .unwrap()
+            .iter()
+            .map(|elf| (elf.index, elf.items, elf.calories))
+            .collect();
+        assert_eq!(top, vec![(4, 3, 24000), (3, 2, 11000)]);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let sorted = [1000, 2000, 3000, 4000, 10000];
+        assert_eq!(percentile(&sorted, 50), 3000.0);
+        assert_eq!(percentile(&sorted, 75), 4000.0);
+        assert_eq!(percentile(&sorted, 90), 7600.0);
+        assert_eq!(histogram(&sorted, 3), vec![(1000, 4), (4001, 0), (7002, 1)]);
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
@@ -1,128 +1,323 @@
-use aoc_utils::PuzzleInput;
+use aoc_utils::{exit_on_error, print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 2;
 
 fn main() {
+    let mut game = None;
+    let mut optimize = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next().as_deref()) {
+            ("--game", Some("rps")) => game = Some(Game::rock_paper_scissors()),
+            ("--game", Some("rpsls")) => game = Some(Game::rock_paper_scissors_lizard_spock()),
+            ("--game", Some(n)) => match n.parse() {
+                Ok(n) if n % 2 == 1 && n <= MAX_SHAPES => game = Some(Game::cyclic_numbered(n)),
+                _ => usage(),
+            },
+            ("--optimize", Some("shape")) => optimize = Some(Response::Shape),
+            ("--optimize", Some("outcome")) => optimize = Some(Response::Outcome),
+            _ => usage(),
+        }
+    }
+
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    match (game, optimize) {
+        (None, None) => {
+            print_answer("A", solve_a(&input));
+            print_answer("B", solve_b(&input));
+        }
+        (game, Some(response)) => {
+            let game = game.unwrap_or_else(Game::rock_paper_scissors);
+            if response == Response::Shape && game.shapes.len() > MAX_OPTIMIZED_SHAPES {
+                eprintln!(
+                    "error: Too many mappings, at most {} shapes can be optimized",
+                    MAX_OPTIMIZED_SHAPES
+                );
+                std::process::exit(2);
+            }
+
+            let tournament = Tournament::new(&game, response);
+            let mappings = exit_on_error(tournament.optimize(&input));
+            for (symbols, score) in &mappings {
+                println!("{}: {}", tournament.describe_mapping(symbols), score);
+            }
+            let (best, worst) = (&mappings[0], &mappings[mappings.len() - 1]);
+            println!("Max: {} ({})", best.1, tournament.describe_mapping(&best.0));
+            println!("Min: {} ({})", worst.1, tournament.describe_mapping(&worst.0));
+        }
+        (Some(game), None) => {
+            print_answer("A", Tournament::new(&game, Response::Shape).play(&input));
+            print_answer("B", Tournament::new(&game, Response::Outcome).play(&input));
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
-enum HandShape {
-    Rock,
-    Paper,
-    Scissors,
+fn usage() -> ! {
+    eprintln!(
+        "Usage: day02 [--game <rps|rpsls|n>] [--optimize <shape|outcome>], n being an odd number of shapes up to {}",
+        MAX_SHAPES
+    );
+    std::process::exit(2);
 }
 
+/// The most shapes a game can have, so that the shapes of both players can be written as
+/// distinct letters in the strategy guide.
+const MAX_SHAPES: usize = 13;
+
+/// The most shapes for which all mappings of the symbols to shapes are tried by the optimizer.
+const MAX_OPTIMIZED_SHAPES: usize = 8;
+
+/// A shape of a game, identified by its position in `Game::shapes`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct HandShape(usize);
+
 impl HandShape {
-    fn from_char(c: char) -> HandShape {
-        match c {
-            'A' | 'X' => HandShape::Rock,
-            'B' | 'Y' => HandShape::Paper,
-            'C' | 'Z' => HandShape::Scissors,
-            _ => panic!("Invalid hand shape: {}", c),
-        }
+    /// Looks up the shape that is written as `c`, where `symbols` contains the symbol of every
+    /// shape in the order of the game's shapes.
+    fn from_char(c: char, symbols: &[char]) -> Result<HandShape, SolveError> {
+        symbols
+            .iter()
+            .position(|&s| s == c)
+            .map(HandShape)
+            .ok_or_else(|| SolveError::new(format!("Invalid hand shape: {}", c)))
     }
+}
 
-    fn get_value(&self) -> usize {
-        match self {
-            HandShape::Rock => 1,
-            HandShape::Paper => 2,
-            HandShape::Scissors => 3,
-        }
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
+
+    /// Looks up the outcome that is written as `c`, where `symbols` contains the symbols for
+    /// losing, drawing and winning.
+    fn from_char(c: char, symbols: &[char]) -> Result<Outcome, SolveError> {
+        symbols
+            .iter()
+            .position(|&s| s == c)
+            .map(|i| Outcome::ALL[i])
+            .ok_or_else(|| SolveError::new(format!("Invalid desired outcome: {}", c)))
     }
 }
 
-enum DesiredWinState {
-    Win,
-    Draw,
-    Lose,
+struct Shape {
+    name: String,
+    value: usize,
 }
 
-impl DesiredWinState {
-    fn from_char(c: char) -> DesiredWinState {
-        match c {
-            'Z' => DesiredWinState::Win,
-            'Y' => DesiredWinState::Draw,
-            'X' => DesiredWinState::Lose,
-            _ => panic!("Invalid desired win state"),
+/// A game like rock paper scissors, with the shapes, which shape beats which and the scores
+/// described as data.
+struct Game {
+    shapes: Vec<Shape>,
+    /// `beats[a][b]` is true if shape `a` beats shape `b`
+    beats: Vec<Vec<bool>>,
+    /// Score for losing, drawing and winning a round, indexed by `Outcome`
+    outcome_scores: [usize; 3],
+}
+
+impl Game {
+    /// Creates a game where every shape beats the `(n - 1) / 2` shapes before it, wrapping
+    /// around at the start. Every shape is worth its 1-based position in `names`.
+    fn cyclic(names: &[&str]) -> Game {
+        let n = names.len();
+        assert!(n % 2 == 1, "Cyclic games need an odd number of shapes");
+
+        Game {
+            shapes: names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| Shape {
+                    name: name.to_string(),
+                    value: i + 1,
+                })
+                .collect(),
+            beats: (0..n)
+                .map(|a| (0..n).map(|b| (1..=n / 2).any(|d| (b + d) % n == a)).collect())
+                .collect(),
+            outcome_scores: [0, 3, 6],
         }
     }
 
-    fn get_needed_shape(&self, other: HandShape) -> HandShape {
-        match self {
-            DesiredWinState::Win => match other {
-                HandShape::Rock => HandShape::Paper,
-                HandShape::Paper => HandShape::Scissors,
-                HandShape::Scissors => HandShape::Rock,
-            },
-            DesiredWinState::Draw => other,
-            DesiredWinState::Lose => match other {
-                HandShape::Rock => HandShape::Scissors,
-                HandShape::Paper => HandShape::Rock,
-                HandShape::Scissors => HandShape::Paper,
+    fn cyclic_numbered(n: usize) -> Game {
+        let names: Vec<String> = (1..=n).map(|i| format!("Shape {}", i)).collect();
+        Game::cyclic(&names.iter().map(String::as_str).collect::<Vec<_>>())
+    }
+
+    fn rock_paper_scissors() -> Game {
+        Game::cyclic(&["Rock", "Paper", "Scissors"])
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Game {
+        Game::cyclic(&["Rock", "Spock", "Paper", "Lizard", "Scissors"])
+    }
+
+    fn outcome(&self, other: HandShape, own: HandShape) -> Outcome {
+        if self.beats[own.0][other.0] {
+            Outcome::Win
+        } else if self.beats[other.0][own.0] {
+            Outcome::Lose
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    /// Returns the score of a round: the value of the own shape plus the score of the outcome.
+    fn calculate_score(&self, other: HandShape, own: HandShape) -> usize {
+        self.outcome_scores[self.outcome(other, own) as usize] + self.shapes[own.0].value
+    }
+
+    /// Returns the best scoring shape that leads to the desired outcome against `other`.
+    fn get_needed_shape(&self, other: HandShape, outcome: Outcome) -> Option<HandShape> {
+        (0..self.shapes.len())
+            .map(HandShape)
+            .filter(|own| self.outcome(other, *own) == outcome)
+            .max_by_key(|own| self.shapes[own.0].value)
+    }
+}
+
+/// How the second column of the strategy guide is interpreted.
+#[derive(Copy, Clone, PartialEq)]
+enum Response {
+    /// The shape to play, written as the last letters of the alphabet (X, Y, Z for three shapes)
+    Shape,
+    /// The desired outcome, X meaning lose, Y draw and Z win
+    Outcome,
+}
+
+/// Plays a strategy guide where the opponent's shapes are written as the first letters of the
+/// alphabet (A, B, C for three shapes).
+#[derive(Clone)]
+struct Tournament<'a> {
+    game: &'a Game,
+    response: Response,
+    opponent_symbols: Vec<char>,
+    /// Symbols of the shapes or outcomes in the second column, depending on `response`
+    response_symbols: Vec<char>,
+}
+
+impl<'a> Tournament<'a> {
+    fn new(game: &'a Game, response: Response) -> Self {
+        let n = game.shapes.len() as u8;
+        assert!(n as usize <= MAX_SHAPES, "Games can have at most {} shapes", MAX_SHAPES);
+
+        Tournament {
+            game,
+            response,
+            opponent_symbols: (b'A'..b'A' + n).map(char::from).collect(),
+            response_symbols: match response {
+                Response::Shape => (b'Z' + 1 - n..=b'Z').map(char::from).collect(),
+                Response::Outcome => vec!['X', 'Y', 'Z'],
             },
         }
     }
+
+    /// Returns the shapes played in a line of the strategy guide.
+    fn parse_round(&self, line: &str) -> Result<(HandShape, HandShape), SolveError> {
+        let (a, b) = parse_columns(line)?;
+        let other = HandShape::from_char(a, &self.opponent_symbols)?;
+
+        let own = match self.response {
+            Response::Shape => HandShape::from_char(b, &self.response_symbols)?,
+            Response::Outcome => {
+                let outcome = Outcome::from_char(b, &self.response_symbols)?;
+                self.game.get_needed_shape(other, outcome).ok_or_else(|| {
+                    SolveError::new(format!(
+                        "No shape leads to {:?} against {}",
+                        outcome, self.game.shapes[other.0].name
+                    ))
+                })?
+            }
+        };
+
+        Ok((other, own))
+    }
+
+    /// Returns the total score of following the strategy guide.
+    fn play(&self, input: &PuzzleInput) -> Result<usize, SolveError> {
+        let rounds = input.parse_lines(|line| self.parse_round(line))?;
+
+        Ok(rounds
+            .iter()
+            .map(|&(other, own)| self.game.calculate_score(other, own))
+            .sum())
+    }
+
+    /// Plays the strategy guide with every possible assignment of the symbols in the second
+    /// column to shapes or outcomes. Returns the symbols of each assignment, in the order of
+    /// `response_symbols`, with the total score, highest score first.
+    fn optimize(&self, input: &PuzzleInput) -> Result<Vec<(Vec<char>, usize)>, SolveError> {
+        let mut mappings = permutations(&self.response_symbols)
+            .into_iter()
+            .map(|symbols| {
+                let tournament = Tournament {
+                    response_symbols: symbols.clone(),
+                    ..self.clone()
+                };
+                tournament.play(input).map(|score| (symbols, score))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        mappings.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        Ok(mappings)
+    }
+
+    /// Formats an assignment of symbols like "X=Rock Y=Paper Z=Scissors".
+    fn describe_mapping(&self, symbols: &[char]) -> String {
+        let mut names: Vec<(char, String)> = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, &symbol)| match self.response {
+                Response::Shape => (symbol, self.game.shapes[i].name.clone()),
+                Response::Outcome => (symbol, format!("{:?}", Outcome::ALL[i])),
+            })
+            .collect();
+        names.sort();
+
+        names
+            .iter()
+            .map(|(symbol, name)| format!("{}={}", symbol, name))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
-fn calculate_score(strategy: (HandShape, HandShape)) -> usize {
-    match strategy {
-        (other, HandShape::Rock) => match other {
-            HandShape::Rock => 3,
-            HandShape::Paper => 0,
-            HandShape::Scissors => 6,
-        },
-        (other, HandShape::Paper) => match other {
-            HandShape::Rock => 6,
-            HandShape::Paper => 3,
-            HandShape::Scissors => 0,
-        },
-        (other, HandShape::Scissors) => match other {
-            HandShape::Rock => 0,
-            HandShape::Paper => 6,
-            HandShape::Scissors => 3,
-        },
-    }
-}
-
-fn solve_a(input: &PuzzleInput) -> usize {
-    let strategy: Vec<_> = input
-        .lines()
-        .map(|line| {
-            let shapes: Vec<_> = line
-                .split(' ')
-                .map(|s| HandShape::from_char(s.chars().next().unwrap()))
-                .collect();
-            (shapes[0], shapes[1])
-        })
-        .collect();
-
-    strategy
-        .iter()
-        .map(|s| calculate_score(*s) + s.1.get_value())
-        .sum()
-}
-
-fn solve_b(input: &PuzzleInput) -> usize {
-    let strategy: Vec<_> = input
-        .lines()
-        .map(|line| {
-            let shapes: Vec<_> = line.split(' ').collect();
-            (
-                HandShape::from_char(shapes[0].chars().next().unwrap()),
-                DesiredWinState::from_char(shapes[1].chars().next().unwrap()),
-            )
-        })
-        .collect();
+/// Returns all orderings of the given items.
+fn permutations(items: &[char]) -> Vec<Vec<char>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
 
-    strategy
-        .iter()
-        .map(|s| {
-            let needed_shape = s.1.get_needed_shape(s.0);
-            calculate_score((s.0, needed_shape)) + needed_shape.get_value()
+    (0..items.len())
+        .flat_map(|i| {
+            let mut rest = items.to_vec();
+            let first = rest.remove(i);
+            permutations(&rest).into_iter().map(move |mut permutation| {
+                permutation.insert(0, first);
+                permutation
+            })
         })
-        .sum()
+        .collect()
+}
+
+/// Splits a strategy guide line like "A Y" into its two columns.
+fn parse_columns(line: &str) -> Result<(char, char), SolveError> {
+    let mut columns = line.split(' ').map(|s| s.chars().next());
+    match (columns.next(), columns.next(), columns.next()) {
+        (Some(Some(a)), Some(Some(b)), None) => Ok((a, b)),
+        _ => Err(SolveError::new("Expected two columns separated by a space")),
+    }
+}
+
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    Tournament::new(&Game::rock_paper_scissors(), Response::Shape).play(input)
+}
+
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    Tournament::new(&Game::rock_paper_scissors(), Response::Outcome).play(input)
 }
 
 #[cfg(test)]
@@ -136,17 +331,71 @@ C Z";
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 15);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 15);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 12);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 12);
+    }
+
+    #[test]
+    fn test_invalid_shape() {
+        let error = solve_a(&PuzzleInput::new("A Y\nB Q")).unwrap_err();
+        assert_eq!(error.line_number(), Some(2));
+        assert_eq!(error.line(), Some("B Q"));
+    }
+
+    #[test]
+    fn test_rock_paper_scissors_lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let shape = |name| HandShape(game.shapes.iter().position(|s| s.name == name).unwrap());
+        let beats = |a, b| game.outcome(shape(b), shape(a)) == Outcome::Win;
+
+        for (a, b) in [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ] {
+            assert!(beats(a, b) && !beats(b, a), "{} should beat {}", a, b);
+        }
+
+        // Rock against Spock wins, Scissors against Scissors draws and Paper against Spock loses
+        let input = PuzzleInput::new("A W\nE Z\nC W");
+        assert_eq!(Tournament::new(&game, Response::Shape).play(&input).unwrap(), 8 + 8 + 2);
+    }
+
+    #[test]
+    fn test_optimize() {
+        let game = Game::rock_paper_scissors();
+        let input = PuzzleInput::new(TEST_INPUT);
+
+        let shapes = Tournament::new(&game, Response::Shape).optimize(&input).unwrap();
+        assert_eq!(shapes.len(), 6);
+        assert!(shapes.contains(&(vec!['X', 'Y', 'Z'], 15)));
+        assert_eq!(shapes[0].1, 24);
+        assert_eq!(shapes[5].1, 6);
+
+        let outcomes = Tournament::new(&game, Response::Outcome).optimize(&input).unwrap();
+        assert!(outcomes.contains(&(vec!['X', 'Y', 'Z'], 12)));
+        assert_eq!(outcomes[0], (vec!['Y', 'Z', 'X'], 18));
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
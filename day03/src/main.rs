@@ -1,99 +1,210 @@
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 3;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
 }
 
-fn calculate_priority_points(c: &char) -> u32 {
+fn calculate_priority_points(c: &char) -> Result<u32, SolveError> {
     match c {
-        'a'..='z' => *c as u32 - 96,
-        'A'..='Z' => *c as u32 - 38,
-        _ => panic!("Invalid character"),
+        'a'..='z' => Ok(*c as u32 - 96),
+        'A'..='Z' => Ok(*c as u32 - 38),
+        _ => Err(SolveError::new(format!("Invalid item type: {}", c))),
+    }
+}
+
+/// A set of item types, stored as one bit per item type at the position of its priority.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    /// The set containing all 52 item types.
+    const ALL: ItemSet = ItemSet(((1 << 52) - 1) << 1);
+
+    fn parse(items: &str) -> Result<ItemSet, SolveError> {
+        items.chars().try_fold(ItemSet(0), |set, c| {
+            Ok(ItemSet(set.0 | 1 << calculate_priority_points(&c)?))
+        })
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    /// Returns the priorities of the item types in the set, lowest first.
+    fn priorities(self) -> impl Iterator<Item = u32> {
+        (1..=52).filter(move |p| self.0 & 1 << p != 0)
     }
 }
 
 #[derive(Clone)]
 struct Rucksack {
-    content: String,
+    compartments: [ItemSet; 2],
 }
 
 impl Rucksack {
-    fn new(content: String) -> Self {
-        Self {
-            content,
-        }
+    fn new(content: &str) -> Result<Self, SolveError> {
+        // Valid item types are ASCII letters, so the content can be split at any byte after that
+        ItemSet::parse(content)?;
+        let (first, second) = content.split_at(content.len() / 2);
+
+        Ok(Self {
+            compartments: [ItemSet::parse(first)?, ItemSet::parse(second)?],
+        })
     }
 
-    fn calculate_priority(&self) -> u32 {
-        let first_compartment: Vec<_> = self.content.chars().take(self.content.len() / 2).collect();
-        let second_compartment: Vec<_> =
-            self.content.chars().skip(self.content.len() / 2).collect();
+    fn items(&self) -> ItemSet {
+        ItemSet(self.compartments[0].0 | self.compartments[1].0)
+    }
 
-        let duplicate = first_compartment
-            .iter()
-            .find(|c| second_compartment.contains(c))
-            .unwrap();
+    fn shared_items(&self) -> ItemSet {
+        self.compartments[0].intersection(self.compartments[1])
+    }
 
-        calculate_priority_points(duplicate)
+    fn calculate_priority(&self) -> Result<u32, SolveError> {
+        self.shared_items()
+            .priorities()
+            .next()
+            .ok_or_else(|| SolveError::new("No item type is in both compartments"))
     }
 }
 
 struct ElvGroup {
-    rucksacks: [Rucksack; 3],
+    rucksacks: Vec<Rucksack>,
 }
 
 impl ElvGroup {
-    fn new(rucksacks: Vec<&Rucksack>) -> Self {
-        Self {
-            rucksacks: [
-                rucksacks[0].clone(),
-                rucksacks[1].clone(),
-                rucksacks[2].clone(),
-            ],
-        }
+    fn new(rucksacks: Vec<Rucksack>) -> Self {
+        Self { rucksacks }
     }
 
-    fn calculate_priority(&self) -> u32 {
-        let badge = self.rucksacks[0]
-            .content
-            .chars()
-            .find(|c| {
-                self.rucksacks[1].content.contains(*c) && self.rucksacks[2].content.contains(*c)
-            })
-            .unwrap();
+    /// Returns the item types carried by every elf of the group.
+    fn badges(&self) -> ItemSet {
+        self.rucksacks
+            .iter()
+            .fold(ItemSet::ALL, |badges, rucksack| badges.intersection(rucksack.items()))
+    }
 
-        calculate_priority_points(&badge)
+    fn calculate_priority(&self) -> Result<u32, SolveError> {
+        self.badges()
+            .priorities()
+            .next()
+            .ok_or_else(|| SolveError::new("No badge item type is shared by the group"))
     }
 }
 
-fn solve_a(input: &PuzzleInput) -> u32 {
+/// Checks that every rucksack has an even number of items and exactly one item type in both
+/// compartments. Returns one error for every rucksack breaking these rules.
+fn validate_rucksacks(input: &PuzzleInput) -> Vec<SolveError> {
     input
-        .lines()
-        .map(Rucksack::new)
-        .map(|rucksack| rucksack.calculate_priority())
-        .sum()
+        .numbered_lines()
+        .filter_map(|(number, line)| {
+            let violation = if let Err(e) = ItemSet::parse(line) {
+                e
+            } else if line.len() % 2 != 0 {
+                SolveError::new(format!("Rucksack has an odd number of items ({})", line.len()))
+            } else {
+                let shared = Rucksack::new(line).ok()?.shared_items().priorities().count();
+                if shared == 1 {
+                    return None;
+                }
+                SolveError::new(format!(
+                    "Expected exactly one item type in both compartments, found {}",
+                    shared
+                ))
+            };
+            Some(violation.at_line(number, line))
+        })
+        .collect()
 }
 
-fn solve_b(input: &PuzzleInput) -> u32 {
-    let rucksacks: Vec<_> = input
-        .lines()
-        .map(Rucksack::new)
-        .collect();
+/// Checks that the rucksacks can be split into groups of `group_size` elves which share
+/// exactly one badge item type. Returns one error for every line breaking these rules.
+fn validate_groups(input: &PuzzleInput, group_size: usize) -> Vec<SolveError> {
+    if group_size == 0 {
+        return vec![SolveError::new("Groups need at least one elf")];
+    }
 
-    let mut count = 0;
-    for i in 0..rucksacks.len() {
-        if i % 3 != 0 {
+    let lines: Vec<_> = input.numbered_lines().collect();
+    let mut violations = vec![];
+
+    for group in lines.chunks(group_size) {
+        if group.len() < group_size {
+            violations.extend(group.iter().map(|(number, line)| {
+                SolveError::new(format!("Rucksack is not part of a complete group of {}", group_size))
+                    .at_line(*number, line)
+            }));
             continue;
         }
 
-        let elv_group = ElvGroup::new(rucksacks.iter().skip(i).take(3).collect());
-        count += elv_group.calculate_priority();
+        let rucksacks = group.iter().map(|(_, line)| Rucksack::new(line));
+        if let Ok(rucksacks) = rucksacks.collect::<Result<Vec<_>, _>>() {
+            let badges = ElvGroup::new(rucksacks).badges().priorities().count();
+            if badges != 1 {
+                let (number, line) = group[0];
+                violations.push(
+                    SolveError::new(format!(
+                        "Expected exactly one badge item type in the group starting here, found {}",
+                        badges
+                    ))
+                    .at_line(number, line),
+                );
+            }
+        }
     }
 
-    count
+    violations
+}
+
+/// Turns the errors found by a validation pass into a single error listing all of them.
+fn check_violations(mut violations: Vec<SolveError>) -> Result<(), SolveError> {
+    violations.sort_by_key(|v| v.line_number());
+    violations.dedup();
+
+    match violations.len() {
+        0 => Ok(()),
+        1 => Err(violations.remove(0)),
+        n => Err(SolveError::new(format!(
+            "Found {} invalid rucksacks or groups:\n{}",
+            n,
+            violations.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("\n")
+        ))),
+    }
+}
+
+fn solve_a(input: &PuzzleInput) -> Result<u32, SolveError> {
+    check_violations(validate_rucksacks(input))?;
+
+    Ok(input
+        .parse_lines(|line| Rucksack::new(line)?.calculate_priority())?
+        .iter()
+        .sum())
+}
+
+/// Sums the priorities of the badges of all groups of `group_size` elves.
+fn sum_badge_priorities(input: &PuzzleInput, group_size: usize) -> Result<u32, SolveError> {
+    let mut violations = validate_rucksacks(input);
+    violations.extend(validate_groups(input, group_size));
+    check_violations(violations)?;
+
+    let rucksacks = input.parse_lines(Rucksack::new)?;
+    let lines: Vec<_> = input.numbered_lines().collect();
+
+    let mut count = 0;
+    for (i, group) in rucksacks.chunks_exact(group_size).enumerate() {
+        let (number, line) = lines[i * group_size];
+        count += ElvGroup::new(group.to_vec())
+            .calculate_priority()
+            .map_err(|e| e.at_line(number, line))?;
+    }
+
+    Ok(count)
+}
+
+fn solve_b(input: &PuzzleInput) -> Result<u32, SolveError> {
+    sum_badge_priorities(input, 3)
 }
 
 #[cfg(test)]
@@ -110,17 +221,49 @@ CrZsJsPPZsGzwwsLwLmpwMDw";
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 157);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 157);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 70);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 70);
+    }
+
+    #[test]
+    fn test_item_set() {
+        let first = ItemSet::parse("vJrwpWtwJgWr").unwrap();
+        let second = ItemSet::parse("hcsFMMfFFhFp").unwrap();
+        assert_eq!(first.intersection(second).priorities().collect::<Vec<_>>(), vec![16]);
+        assert_eq!(ItemSet::ALL.priorities().count(), 52);
+
+        let pairs = PuzzleInput::new("abAb\nAcdc\nxBxy\nBzqz");
+        assert_eq!(sum_badge_priorities(&pairs, 2).unwrap(), 27 + 28);
+    }
+
+    #[test]
+    fn test_validation() {
+        let input = PuzzleInput::new("abAb\nAcdcx\nAeAf\nxBxy\nBzqz");
+        let mut violations = validate_rucksacks(&input);
+        violations.extend(validate_groups(&input, 2));
+        let lines: Vec<_> = violations.iter().map(|v| v.line_number().unwrap()).collect();
+        assert_eq!(lines, vec![2, 3, 5]);
+
+        let error = sum_badge_priorities(&input, 2).unwrap_err();
+        assert_eq!(error.line_number(), None);
+        assert!(error.to_string().starts_with("Found 3 invalid rucksacks or groups"));
+
+        let error = sum_badge_priorities(&PuzzleInput::new("abAb"), 0).unwrap_err();
+        assert_eq!(error.to_string(), "Groups need at least one elf");
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
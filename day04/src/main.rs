@@ -1,49 +1,65 @@
-use std::ops::RangeInclusive;
-
-use aoc_utils::PuzzleInput;
+use aoc_utils::interval::{self, Interval};
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 4;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            print_answer("A", solve_a(&input));
+            print_answer("B", solve_b(&input));
+        }
+        ["--coverage"] => print_answer("Sections covered", count_covered_sections(&input)),
+        _ => {
+            eprintln!("Usage: day04 [--coverage]");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn parse_range(range: &str) -> Result<Interval, SolveError> {
+    let (start, end) = range
+        .split_once('-')
+        .ok_or_else(|| SolveError::new(format!("Invalid section range: {}", range)))?;
+    let (start, end) = (start.parse::<u32>()?, end.parse::<u32>()?);
+    Interval::new(start.into(), end.into())
+        .ok_or_else(|| SolveError::new(format!("Section range ends before it starts: {}", range)))
 }
 
-fn parse_input(input: &PuzzleInput) -> Vec<(RangeInclusive<usize>, RangeInclusive<usize>)>{
-    input
-    .lines()
-    .map(|line| {
-        let mut ranges = line.split(',')
-            .map(|range| {
-                let mut range = range.split('-');
-                let start = range.next().unwrap().parse().unwrap();
-                let end = range.next().unwrap().parse().unwrap();
-                start..=end
-            });
-        
-        (ranges.next().unwrap(), ranges.next().unwrap())
+type Assignment = (Interval, Interval);
+
+fn parse_input(input: &PuzzleInput) -> Result<Vec<Assignment>, SolveError> {
+    input.parse_lines(|line| {
+        let (first, second) = line
+            .split_once(',')
+            .ok_or_else(|| SolveError::new("Expected two ranges separated by a comma"))?;
+
+        Ok((parse_range(first)?, parse_range(second)?))
     })
-    .collect()
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    parse_input(input)
-    .iter()
-        .filter(|(r1, r2)| {
-            // one range fully contains the other
-            r1.contains(r2.start()) && r1.contains(r2.end()) || r2.contains(r1.start()) && r2.contains(r1.end())
-        })
-        .count()
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    Ok(parse_input(input)?
+        .iter()
+        .filter(|(r1, r2)| r1.contains(r2) || r2.contains(r1))
+        .count())
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    parse_input(input)
-    .iter()
-    .filter(|(r1, r2)| {
-        // one range overlaps with the other
-        r1.start() <= r2.end() && r2.start() <= r1.end()
-    })
-    .count()
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    Ok(parse_input(input)?
+        .iter()
+        .filter(|(r1, r2)| r1.overlaps(r2))
+        .count())
+}
+
+/// Counts the sections that are assigned to at least one elf.
+fn count_covered_sections(input: &PuzzleInput) -> Result<u64, SolveError> {
+    let assignments = parse_input(input)?;
+    Ok(interval::covered_length(
+        assignments.iter().flat_map(|&(r1, r2)| [r1, r2]),
+    ))
 }
 
 #[cfg(test)]
@@ -60,17 +76,27 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 2);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 2);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 4);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_count_covered_sections() {
+        assert_eq!(count_covered_sections(&PuzzleInput::new(TEST_INPUT)).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
@@ -1,6 +1,59 @@
-use aoc_utils::PuzzleInput;
+use std::fmt;
+use std::path::Path;
+
+use aoc_utils::{exit_on_error, print_answer, write_frames, PuzzleInput, SolveError};
 const DAY: u8 = 5;
 
+fn main() {
+    let usage = || -> ! {
+        eprintln!("Usage: day05 [--crane <9000|9001|k>] [--trace [--frames <directory>]]");
+        std::process::exit(2);
+    };
+
+    let mut crane = None;
+    let mut trace_steps = false;
+    let mut frames_directory = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--crane" => {
+                let model = args.next().and_then(|c| CraneModel::parse(&c));
+                crane = Some(model.unwrap_or_else(|| usage()));
+            }
+            "--trace" => trace_steps = true,
+            "--frames" => frames_directory = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+    if frames_directory.is_some() && !trace_steps {
+        usage();
+    }
+
+    let input = PuzzleInput::get_input(DAY);
+    if !trace_steps {
+        match crane {
+            None => {
+                print_answer("A", solve_a(&input));
+                print_answer("B", solve_b(&input));
+            }
+            Some(crane) => print_answer(&format!("{:?}", crane), get_top_with(&input, crane)),
+        }
+        return;
+    }
+
+    let crane = crane.unwrap_or(CraneModel::CrateMover9000);
+    let result = trace(&input, crane).and_then(|frames| match &frames_directory {
+        Some(directory) => write_frames(&frames, Path::new(directory)),
+        None => {
+            println!("{}", frames.join("\n\n"));
+            Ok(())
+        }
+    });
+
+    exit_on_error(result);
+}
+
 struct Instruction {
     count: u8,
     source: usize,
@@ -8,105 +61,312 @@ struct Instruction {
 }
 
 impl Instruction {
-    fn new(s: &str) -> Self {
+    fn new(s: &str) -> Result<Self, SolveError> {
         // example: "move 2 from 2 to 1"
-        let mut parts = s.split_whitespace();
-        parts.next();
-        let count = parts.next().unwrap().parse().unwrap();
-        parts.next();
-        let source: usize = parts.next().unwrap().parse().unwrap();
-        parts.next();
-        let target: usize = parts.next().unwrap().parse().unwrap();
-        Self {
+        let parts: Vec<_> = s.split_whitespace().collect();
+        let (count, source, target) = match parts[..] {
+            ["move", count, "from", source, "to", target] => (count, source, target),
+            _ => return Err(SolveError::new("Expected instruction like \"move 2 from 2 to 1\"")),
+        };
+
+        let count = count.parse()?;
+        let source: usize = source.parse()?;
+        let target: usize = target.parse()?;
+        if source == 0 || target == 0 {
+            return Err(SolveError::new("Stack numbers start at 1"));
+        }
+
+        Ok(Self {
             count,
             source: source - 1, // convert to zero based indexing
             target: target - 1,
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.source + 1, self.target + 1)
+    }
+}
+
+/// Called after every executed instruction with the stacks after the instruction.
+type Observer<'a> = dyn FnMut(&Instruction, &[Vec<char>]) + 'a;
+
+/// How a crane moves the crates of an instruction from one stack to another.
+#[derive(Copy, Clone, Debug)]
+enum CraneModel {
+    /// Moves one crate at a time, so the moved crates end up in reverse order
+    CrateMover9000,
+    /// Moves all crates of an instruction at once, keeping their order
+    CrateMover9001,
+    /// Moves at most the given number of crates at once, keeping the order of each lift
+    Bounded(usize),
+}
+
+impl CraneModel {
+    /// Parses a crane model like "9000", "9001" or "3" for a crane lifting at most 3 crates.
+    fn parse(s: &str) -> Option<CraneModel> {
+        match s {
+            "9000" => Some(CraneModel::CrateMover9000),
+            "9001" => Some(CraneModel::CrateMover9001),
+            _ => match s.parse() {
+                Ok(0) | Err(_) => None,
+                Ok(capacity) => Some(CraneModel::Bounded(capacity)),
+            },
+        }
+    }
+
+    /// Returns how many crates the crane can lift at once.
+    fn capacity(&self) -> usize {
+        match self {
+            CraneModel::CrateMover9000 => 1,
+            CraneModel::CrateMover9001 => usize::MAX,
+            CraneModel::Bounded(capacity) => *capacity,
         }
     }
 }
 
+/// Removes the top `count` crates of a stack, returning them from bottom to top.
+fn lift_crates(
+    crate_stacks: &mut [Vec<char>],
+    stack: usize,
+    count: usize,
+) -> Result<Vec<char>, SolveError> {
+    let crates = &mut crate_stacks[stack];
+    if crates.len() < count {
+        return Err(SolveError::new(format!(
+            "Can't move {} crates from stack {} with {} crates",
+            count,
+            stack + 1,
+            crates.len()
+        )));
+    }
+
+    Ok(crates.split_off(crates.len() - count))
+}
+
 struct CrateState {
     crate_stacks: Vec<Vec<char>>,
     instructions: Vec<Instruction>,
 }
 
 impl CrateState {
-    fn new(input: &PuzzleInput) -> Self {
-        let parts = input.raw_input.split("\n\n").collect::<Vec<_>>();
-        let crate_stacks = Self::parse_state(parts[0]);
-        let instructions = parts[1].lines().map(Instruction::new).collect();
-        Self {
+    fn new(input: &PuzzleInput) -> Result<Self, SolveError> {
+        let blocks = input.blocks();
+        if blocks.len() != 2 {
+            return Err(SolveError::new(
+                "Expected a stack drawing and a list of instructions separated by an empty line",
+            ));
+        }
+
+        let crate_stacks = Self::parse_state(&blocks[0])?;
+
+        let mut instructions = Vec::new();
+        for (number, line) in &blocks[1] {
+            let instruction = Instruction::new(line).map_err(|e| e.at_line(*number, line))?;
+            if instruction.source >= crate_stacks.len() || instruction.target >= crate_stacks.len() {
+                return Err(SolveError::new(format!(
+                    "Only {} stacks exist",
+                    crate_stacks.len()
+                ))
+                .at_line(*number, line));
+            }
+            instructions.push(instruction);
+        }
+
+        Ok(Self {
             crate_stacks,
             instructions,
-        }
+        })
     }
 
-    fn parse_state(s: &str) -> Vec<Vec<char>> {
+    /// Parses a drawing of the stacks. The positions of the stacks are taken from the numbers
+    /// in the last line, every crate has to be drawn right above the last digit of a number.
+    fn parse_state(lines: &[(usize, &str)]) -> Result<Vec<Vec<char>>, SolveError> {
         // Example state:
         //     [D]
         // [N] [C]
         // [Z] [M] [P]
         //  1   2   3
-        let count = s.lines().last().unwrap().split_whitespace().count();
-        let mut stacks = vec![Vec::new(); count];
-        for line in s.lines() {
-            for (i, stack) in stacks.iter_mut().enumerate().take(count) {
-                if let Some(c) = line.chars().nth(1 + 4 * i) {
-                    if c != ' ' {
-                        stack.insert(0, c);
+        let (&(footer_number, footer), rows) = lines
+            .split_last()
+            .ok_or_else(|| SolveError::new("Missing stack drawing"))?;
+
+        let columns = Self::parse_footer(footer).map_err(|e| e.at_line(footer_number, footer))?;
+
+        // Filled from top to bottom and reversed at the end
+        let mut stacks = vec![Vec::new(); columns.len()];
+        for &(number, line) in rows {
+            let crates = Self::parse_row(line, &columns).map_err(|e| e.at_line(number, line))?;
+
+            for (i, stack) in stacks.iter_mut().enumerate() {
+                match crates[i] {
+                    Some(c) => stack.push(c),
+                    None if !stack.is_empty() => {
+                        return Err(SolveError::new(format!(
+                            "Crate {} of stack {} is floating above an empty position",
+                            stack[stack.len() - 1],
+                            i + 1
+                        ))
+                        .at_line(number, line))
                     }
+                    None => {}
                 }
             }
         }
 
-        stacks
+        for stack in &mut stacks {
+            stack.reverse();
+        }
+
+        Ok(stacks)
     }
 
-    fn execute_part1(&mut self) {
-        for instruction in &self.instructions {
-            for _ in 0..instruction.count {
-                let c = self.crate_stacks[instruction.source].pop().unwrap();
-                self.crate_stacks[instruction.target].push(c);
+    /// Returns the column of every stack, which is the position of the last digit of its number.
+    fn parse_footer(footer: &str) -> Result<Vec<usize>, SolveError> {
+        let chars: Vec<char> = footer.chars().collect();
+        let mut columns = Vec::new();
+
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == ' ' {
+                i += 1;
+                continue;
             }
+
+            let start = i;
+            while i < chars.len() && chars[i] != ' ' {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            if number != (columns.len() + 1).to_string() {
+                return Err(SolveError::new(format!(
+                    "Expected stack number {} but found {}",
+                    columns.len() + 1,
+                    number
+                )));
+            }
+            columns.push(i - 1);
         }
+
+        if columns.is_empty() {
+            return Err(SolveError::new("Expected a line with the stack numbers"));
+        }
+
+        Ok(columns)
     }
 
-    fn execute_part2(&mut self) {
-        for instruction in &self.instructions {
-            let mut crates = Vec::new();
-            for _ in 0..instruction.count {
-                crates.insert(0, self.crate_stacks[instruction.source].pop().unwrap());
+    /// Returns the crate of every stack in a row of the drawing, if there is one.
+    fn parse_row(line: &str, columns: &[usize]) -> Result<Vec<Option<char>>, SolveError> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut crates = vec![None; columns.len()];
+
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i..] {
+                [' ', ..] => i += 1,
+                ['[', c, ']', ..] if c.is_ascii_alphabetic() => {
+                    let stack = columns
+                        .iter()
+                        .position(|&column| column == i + 1)
+                        .ok_or_else(|| {
+                            SolveError::new(format!("Crate {} is not above a stack number", c))
+                        })?;
+                    crates[stack] = Some(c);
+                    i += 3;
+                }
+                _ => {
+                    return Err(SolveError::new(format!(
+                        "Expected a crate like [A] at position {}",
+                        i + 1
+                    )))
+                }
             }
-            for c in crates {
-                self.crate_stacks[instruction.target].push(c);
+        }
+
+        Ok(crates)
+    }
+
+    fn execute(&mut self, crane: CraneModel, observe: &mut Observer) -> Result<(), SolveError> {
+        for instruction in &self.instructions {
+            let mut remaining = instruction.count as usize;
+            while remaining > 0 {
+                let lift = remaining.min(crane.capacity());
+                let crates = lift_crates(&mut self.crate_stacks, instruction.source, lift)?;
+                self.crate_stacks[instruction.target].extend(crates);
+                remaining -= lift;
             }
+            observe(instruction, &self.crate_stacks);
         }
+
+        Ok(())
     }
 
     fn get_top(&self) -> String {
         self.crate_stacks
             .iter()
-            .map(|s| s.last().unwrap())
+            .filter_map(|s| s.last())
             .collect()
     }
 }
 
-fn main() {
-    let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+/// Draws the stacks in the same format as the drawing in the puzzle input, which can be read
+/// again by `CrateState::parse_state`. The stack numbers are aligned to the right below the
+/// crates, which works for up to 99 stacks.
+fn format_drawing(crate_stacks: &[Vec<char>]) -> String {
+    let height = crate_stacks.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut lines: Vec<String> = (0..height)
+        .rev()
+        .map(|level| {
+            crate_stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+
+    lines.push(
+        (1..=crate_stacks.len())
+            .map(|i| format!("{:>2} ", i))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    lines.join("\n")
+}
+
+/// Executes the instructions and returns a frame for the initial stacks and one after every
+/// instruction. Each frame shows the executed instruction and the drawing of the stacks.
+fn trace(input: &PuzzleInput, crane: CraneModel) -> Result<Vec<String>, SolveError> {
+    let mut state = CrateState::new(input)?;
+    let mut frames = vec![format!("initial\n\n{}", format_drawing(&state.crate_stacks))];
+
+    let mut record = |instruction: &Instruction, stacks: &[Vec<char>]| {
+        frames.push(format!("{}\n\n{}", instruction, format_drawing(stacks)));
+    };
+    state.execute(crane, &mut record)?;
+
+    Ok(frames)
+}
+
+/// Returns the crates on top of the stacks after executing the instructions with the crane.
+fn get_top_with(input: &PuzzleInput, crane: CraneModel) -> Result<String, SolveError> {
+    let mut state = CrateState::new(input)?;
+    state.execute(crane, &mut |_, _| {})?;
+    Ok(state.get_top())
 }
 
-fn solve_a(input: &PuzzleInput) -> String {
-    let mut state = CrateState::new(input);
-    state.execute_part1();
-    state.get_top()
+fn solve_a(input: &PuzzleInput) -> Result<String, SolveError> {
+    get_top_with(input, CraneModel::CrateMover9000)
 }
 
-fn solve_b(input: &PuzzleInput) -> String {
-    let mut state = CrateState::new(input);
-    state.execute_part2();
-    state.get_top()
+fn solve_b(input: &PuzzleInput) -> Result<String, SolveError> {
+    get_top_with(input, CraneModel::CrateMover9001)
 }
 
 #[cfg(test)]
@@ -126,17 +386,93 @@ move 1 from 1 to 2";
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), "CMZ");
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), "CMZ");
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), "MCD");
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), "MCD");
+    }
+
+    #[test]
+    fn test_invalid_instruction() {
+        let input = TEST_INPUT.replace("move 3 from 1 to 3", "move 3 from 1 to 4");
+        let error = solve_a(&PuzzleInput::new(input)).unwrap_err();
+        assert_eq!(error.line_number(), Some(7));
+        assert_eq!(error.line(), Some("move 3 from 1 to 4"));
+    }
+
+    #[test]
+    fn test_trace() {
+        let frames = trace(&PuzzleInput::new(TEST_INPUT), CraneModel::CrateMover9000).unwrap();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames[0], format!("initial\n\n{}", TEST_INPUT.split("\n\n").next().unwrap()));
+        assert_eq!(
+            frames[4],
+            "move 1 from 1 to 2
+
+        [Z]
+        [N]
+        [D]
+[C] [M] [P]
+ 1   2   3 "
+        );
+    }
+
+    #[test]
+    fn test_bounded_crane() {
+        let input = PuzzleInput::new("[C]\n[B]\n[A]\n 1   2 \n\nmove 3 from 1 to 2");
+        let top = |crane| {
+            let mut state = CrateState::new(&input).unwrap();
+            state.execute(crane, &mut |_, _| {}).unwrap();
+            state.crate_stacks[1].iter().collect::<String>()
+        };
+
+        assert_eq!(top(CraneModel::CrateMover9000), "CBA");
+        assert_eq!(top(CraneModel::CrateMover9001), "ABC");
+        assert_eq!(top(CraneModel::Bounded(2)), "BCA");
+        assert_eq!(top(CraneModel::Bounded(3)), "ABC");
+    }
+
+    fn parse_drawing(drawing: &str) -> Result<Vec<Vec<char>>, SolveError> {
+        let lines: Vec<_> = drawing.lines().enumerate().map(|(i, l)| (i + 1, l)).collect();
+        CrateState::parse_state(&lines)
+    }
+
+    #[test]
+    fn test_drawing_round_trip() {
+        let drawing = TEST_INPUT.split("\n\n").next().unwrap();
+        let stacks = parse_drawing(drawing).unwrap();
+        assert_eq!(stacks, vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]);
+        assert_eq!(format_drawing(&stacks), drawing);
+
+        let trimmed: Vec<_> = drawing.lines().map(str::trim_end).collect();
+        assert_eq!(parse_drawing(&trimmed.join("\n")).unwrap(), stacks);
+
+        let many_stacks: Vec<Vec<char>> = (0..12).map(|i| vec!['A'; i % 4]).collect();
+        assert_eq!(parse_drawing(&format_drawing(&many_stacks)).unwrap(), many_stacks);
+    }
+
+    #[test]
+    fn test_malformed_drawing() {
+        let error = parse_drawing("[A] [B]\n[C]\n 1   2").unwrap_err();
+        assert_eq!(error.line_number(), Some(2));
+
+        let error = parse_drawing("[A] [B\n 1   2").unwrap_err();
+        assert_eq!(error.line_number(), Some(1));
+
+        let error = parse_drawing("[A] [B]\n 1   3").unwrap_err();
+        assert_eq!(error.line_number(), Some(2));
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
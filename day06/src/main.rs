@@ -1,32 +1,251 @@
-use aoc_utils::PuzzleInput;
+use std::collections::VecDeque;
+use std::io::{BufReader, Read};
+
+use aoc_utils::{exit_on_error, print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 6;
 
 fn main() {
-    let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            let input = PuzzleInput::get_input(DAY);
+            print_answer("A", solve_a(&input));
+            print_answer("B", solve_b(&input));
+        }
+        ["--decode", ref frame_types @ ..] => {
+            let frame_types = if frame_types.is_empty() {
+                vec![FrameType::new("packet", 4), FrameType::new("message", 14)]
+            } else {
+                frame_types
+                    .iter()
+                    .map(|t| FrameType::parse(t).unwrap_or_else(|| usage()))
+                    .collect()
+            };
+
+            for frame in Decoder::new(PuzzleInput::open_input(DAY), &frame_types) {
+                let frame = exit_on_error(frame);
+                println!(
+                    "{} at {}: {}",
+                    frame_types[frame.frame_type].name,
+                    frame.position,
+                    String::from_utf8_lossy(&frame.payload)
+                );
+            }
+        }
+        ["--markers", length] => {
+            let length = match length.parse() {
+                Ok(n) if n > 0 => n,
+                _ => usage(),
+            };
+            for marker in find_markers(PuzzleInput::open_input(DAY), length) {
+                println!("{}", exit_on_error(marker));
+            }
+        }
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: day06 [--markers <length> | --decode [<name>=<marker length>...]]");
+    std::process::exit(2);
+}
+
+/// Finds markers in a datastream, which are `length` pairwise different bytes in a row.
+/// Every byte is only looked at when it enters and when it leaves the window.
+struct MarkerDetector {
+    length: usize,
+    window: VecDeque<u8>,
+    /// How often each byte value occurs in the window
+    counts: [usize; 256],
+    distinct: usize,
+}
+
+impl MarkerDetector {
+    fn new(length: usize) -> Self {
+        MarkerDetector {
+            length,
+            window: VecDeque::with_capacity(length + 1),
+            counts: [0; 256],
+            distinct: 0,
+        }
+    }
+
+    /// Adds the next byte of the datastream. Returns true if it completes a marker.
+    fn push(&mut self, byte: u8) -> bool {
+        if self.window.len() == self.length {
+            if let Some(oldest) = self.window.pop_front() {
+                self.counts[oldest as usize] -= 1;
+                if self.counts[oldest as usize] == 0 {
+                    self.distinct -= 1;
+                }
+            }
+        }
+
+        if self.length > 0 {
+            self.window.push_back(byte);
+            self.counts[byte as usize] += 1;
+            if self.counts[byte as usize] == 1 {
+                self.distinct += 1;
+            }
+        }
+
+        self.distinct == self.length
+    }
+}
+
+/// Iterates over the positions of all markers of the given length in the datastream, which
+/// are the numbers of bytes read up to and including the end of each marker. Line breaks are
+/// skipped, as they aren't part of the datastream. The datastream is only read as far as
+/// needed for the next marker.
+fn find_markers<R: Read>(reader: R, length: usize) -> Markers<R> {
+    Markers {
+        bytes: BufReader::new(reader).bytes(),
+        detector: MarkerDetector::new(length),
+        position: 0,
+    }
+}
+
+/// Iterator over the markers of a datastream, see `find_markers`.
+struct Markers<R: Read> {
+    bytes: std::io::Bytes<BufReader<R>>,
+    detector: MarkerDetector,
+    position: usize,
+}
+
+impl<R: Read> Iterator for Markers<R> {
+    type Item = Result<usize, SolveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in self.bytes.by_ref() {
+            let byte = match byte {
+                Ok(b'\n' | b'\r') => continue,
+                Ok(byte) => byte,
+                Err(e) => return Some(Err(SolveError::new(format!("Unable to read input: {}", e)))),
+            };
+
+            self.position += 1;
+            if self.detector.push(byte) {
+                return Some(Ok(self.position));
+            }
+        }
+
+        None
+    }
+}
+
+/// A kind of frame in the datastream, which starts with a marker of the given length.
+struct FrameType {
+    name: String,
+    marker_length: usize,
+}
+
+impl FrameType {
+    fn new(name: &str, marker_length: usize) -> Self {
+        FrameType {
+            name: name.to_string(),
+            marker_length,
+        }
+    }
+
+    /// Parses a frame type like "message=14". Markers must be at least one byte long.
+    fn parse(s: &str) -> Option<FrameType> {
+        let (name, length) = s.split_once('=')?;
+        match length.parse().ok()? {
+            0 => None,
+            length => Some(FrameType::new(name, length)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Frame {
+    /// Index of the frame type in the decoder's frame types
+    frame_type: usize,
+    /// Position of the end of the frame's marker, counted like in `find_markers`
+    position: usize,
+    /// The bytes between the marker and the marker of the next frame
+    payload: Vec<u8>,
+}
+
+/// Splits a datastream into frames. The frame types take turns in the given order: after a
+/// frame of one type, the decoder looks for the marker of the next type, which ends the frame.
+/// Bytes before the first marker aren't part of any frame. Markers never overlap, so the
+/// search for a marker starts right after the previous one.
+struct Decoder<'a, R: Read> {
+    bytes: std::io::Bytes<BufReader<R>>,
+    frame_types: &'a [FrameType],
+    detector: MarkerDetector,
+    /// Type of the frame whose marker is searched for next
+    next_type: usize,
+    current: Option<Frame>,
+    position: usize,
 }
 
-fn find_first_unique_block(input:&PuzzleInput, length: usize) -> Option<usize> {
-    for i in 0..input.raw_input.len() - length - 1 {
-        let mut chars = input.raw_input.chars().skip(i).take(length).collect::<Vec<_>>();
-        chars.sort();
-        chars.dedup(); // dedup requires the chars to be sorted
-        if chars.len() == length {
-            return Some(i + length);
+impl<'a, R: Read> Decoder<'a, R> {
+    fn new(reader: R, frame_types: &'a [FrameType]) -> Self {
+        Decoder {
+            bytes: BufReader::new(reader).bytes(),
+            frame_types,
+            detector: MarkerDetector::new(frame_types[0].marker_length),
+            next_type: 0,
+            current: None,
+            position: 0,
         }
     }
-    None
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    find_first_unique_block(input, 4)
-    .expect("No start-of-packet marker found")
+impl<R: Read> Iterator for Decoder<'_, R> {
+    type Item = Result<Frame, SolveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for byte in self.bytes.by_ref() {
+            let byte = match byte {
+                Ok(b'\n' | b'\r') => continue,
+                Ok(byte) => byte,
+                Err(e) => return Some(Err(SolveError::new(format!("Unable to read input: {}", e)))),
+            };
+
+            self.position += 1;
+            if let Some(frame) = &mut self.current {
+                frame.payload.push(byte);
+            }
+            if !self.detector.push(byte) {
+                continue;
+            }
+
+            // The marker isn't part of the payload of the previous frame
+            let marker_length = self.frame_types[self.next_type].marker_length;
+            let finished = self.current.replace(Frame {
+                frame_type: self.next_type,
+                position: self.position,
+                payload: Vec::new(),
+            });
+
+            self.next_type = (self.next_type + 1) % self.frame_types.len();
+            self.detector = MarkerDetector::new(self.frame_types[self.next_type].marker_length);
+
+            if let Some(mut frame) = finished {
+                frame.payload.truncate(frame.payload.len() - marker_length);
+                return Some(Ok(frame));
+            }
+        }
+
+        self.current.take().map(Ok)
+    }
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    find_first_unique_block(input, 14)
-    .expect("No start-of-packet marker found")
+fn find_first_marker(input: &PuzzleInput, length: usize) -> Result<Option<usize>, SolveError> {
+    find_markers(input.raw_input.as_bytes(), length).next().transpose()
+}
+
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    find_first_marker(input, 4)?
+        .ok_or_else(|| SolveError::new("No start-of-packet marker found"))
+}
+
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    find_first_marker(input, 14)?
+        .ok_or_else(|| SolveError::new("No start-of-message marker found"))
 }
 
 #[cfg(test)]
@@ -36,24 +255,73 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new("bvwbjplbgvbhsrlpgdmjqwftvncz")), 5);
-        assert_eq!(solve_a(&PuzzleInput::new("nppdvjthqldpwncqszvftbrmjlhg")), 6);
-        assert_eq!(solve_a(&PuzzleInput::new("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")), 10);
-        assert_eq!(solve_a(&PuzzleInput::new("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")), 11);
+        assert_eq!(solve_a(&PuzzleInput::new("bvwbjplbgvbhsrlpgdmjqwftvncz")).unwrap(), 5);
+        assert_eq!(solve_a(&PuzzleInput::new("nppdvjthqldpwncqszvftbrmjlhg")).unwrap(), 6);
+        assert_eq!(solve_a(&PuzzleInput::new("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")).unwrap(), 10);
+        assert_eq!(solve_a(&PuzzleInput::new("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")).unwrap(), 11);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new("mjqjpqmgbljsphdztnvjfqwrcgsmlb")), 19);
-        assert_eq!(solve_b(&PuzzleInput::new("bvwbjplbgvbhsrlpgdmjqwftvncz")), 23);
-        assert_eq!(solve_b(&PuzzleInput::new("nppdvjthqldpwncqszvftbrmjlhg")), 23);
-        assert_eq!(solve_b(&PuzzleInput::new("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")), 29);
-        assert_eq!(solve_b(&PuzzleInput::new("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")), 26);
+        assert_eq!(solve_b(&PuzzleInput::new("mjqjpqmgbljsphdztnvjfqwrcgsmlb")).unwrap(), 19);
+        assert_eq!(solve_b(&PuzzleInput::new("bvwbjplbgvbhsrlpgdmjqwftvncz")).unwrap(), 23);
+        assert_eq!(solve_b(&PuzzleInput::new("nppdvjthqldpwncqszvftbrmjlhg")).unwrap(), 23);
+        assert_eq!(solve_b(&PuzzleInput::new("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg")).unwrap(), 29);
+        assert_eq!(solve_b(&PuzzleInput::new("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw")).unwrap(), 26);
+    }
+
+    #[test]
+    fn test_find_markers() {
+        let markers = |stream: &str, length| -> Vec<usize> {
+            find_markers(stream.as_bytes(), length).map(Result::unwrap).collect()
+        };
+        assert_eq!(markers("aabcbd\n", 3), vec![4, 6]);
+        assert_eq!(markers("abcd", 4), vec![4]);
+        assert_eq!(markers("abc", 4), vec![]);
+
+        // the stream is only read up to the requested marker
+        struct Unreadable;
+        impl Read for Unreadable {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("read too far"))
+            }
+        }
+        let mut markers = find_markers("abcd".as_bytes().chain(Unreadable), 4);
+        assert_eq!(markers.next().unwrap().unwrap(), 4);
+        assert!(markers.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_decoder() {
+        let stream = "aaaa abcd aa abcdefghijklmn hiiww wxyz qq".replace(' ', "");
+        let frame_types = [FrameType::new("packet", 4), FrameType::new("message", 14)];
+        let frames: Vec<_> = Decoder::new(stream.as_bytes(), &frame_types)
+            .map(|frame| frame.map(|f| (f.frame_type, f.position, f.payload)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            frames,
+            vec![
+                (0, 8, b"aa".to_vec()),
+                (1, 24, b"hiiww".to_vec()),
+                (0, 33, b"qq".to_vec()),
+            ]
+        );
+
+        assert_eq!(FrameType::parse("message=14").map(|t| t.marker_length), Some(14));
+        assert!(FrameType::parse("message=0").is_none());
+        assert!(FrameType::parse("message").is_none());
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &["mjqjpqmgbljsphdztnvjfqwrcgsmlb"], solve_a, solve_b);
     }
 }
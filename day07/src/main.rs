@@ -1,143 +1,444 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use aoc_utils::PuzzleInput;
+use aoc_utils::{exit_on_error, print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 7;
 
+/// Size of the device's disk.
+const DISK_SIZE: u64 = 70000000;
+/// Free space the update needs.
+const NEEDED_SPACE: u64 = 30000000;
+
+/// The listings that can be printed instead of the answers.
+enum Report {
+    Du(Option<String>),
+    Glob(String),
+    Tree,
+    Top(usize),
+    Candidates,
+}
+
 fn main() {
+    let usage = || -> ! {
+        eprintln!(
+            "Usage: day07 [--du [<path>] | --glob <pattern> | --tree | --top <n> | --candidates]\n\
+             \x20            [--human] [--disk <size>] [--needed <size>]"
+        );
+        std::process::exit(2);
+    };
+    let number = |arg: Option<String>| -> u64 {
+        arg.and_then(|n| n.parse().ok()).unwrap_or_else(|| usage())
+    };
+
+    let mut report = None;
+    let mut human = false;
+    let mut disk_size = DISK_SIZE;
+    let mut needed_space = NEEDED_SPACE;
+
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        let next = match arg.as_str() {
+            "--du" => Report::Du(args.next_if(|a| !a.starts_with("--"))),
+            "--glob" => Report::Glob(args.next().unwrap_or_else(|| usage())),
+            "--tree" => Report::Tree,
+            "--top" => Report::Top(number(args.next()) as usize),
+            "--candidates" => Report::Candidates,
+            "--human" => {
+                human = true;
+                continue;
+            }
+            "--disk" => {
+                disk_size = number(args.next());
+                continue;
+            }
+            "--needed" => {
+                needed_space = number(args.next());
+                continue;
+            }
+            _ => usage(),
+        };
+        if report.replace(next).is_some() {
+            usage();
+        }
+    }
+
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    let report = match report {
+        Some(report) => report,
+        None => {
+            print_answer("A", solve_a(&input));
+            print_answer("B", solve_b(&input));
+            return;
+        }
+    };
+
+    let root = exit_on_error(parse_input(&input));
+    let listing = match report {
+        Report::Du(None) => root.du(),
+        Report::Du(Some(path)) => {
+            let dir = exit_on_error(root.get(&path).ok_or(format!("No such directory: {}", path)));
+            dir.du()
+                .into_iter()
+                .map(|(sub_path, size)| {
+                    match sub_path.strip_prefix('/').filter(|s| !s.is_empty()) {
+                        Some(sub_path) => (join_path(&path, sub_path), size),
+                        None => (path.clone(), size),
+                    }
+                })
+                .collect()
+        }
+        Report::Glob(pattern) => root.glob(&pattern),
+        Report::Tree => {
+            print!("{}", render_tree(&root, human));
+            return;
+        }
+        Report::Top(n) => largest_directories(&root, n),
+        Report::Candidates => deletion_candidates(&root, disk_size, needed_space),
+    };
+
+    for (path, size) in listing {
+        println!("{}\t{}", format_size(size, human), path);
+    }
 }
 
+/// A directory of the device's filesystem. The total size of every directory is cached and
+/// kept up to date when files are added below it.
+#[derive(Debug)]
 struct Directory {
-    sub_directories: HashMap<String, Directory>,
-    files: HashMap<String, u32>,
+    sub_directories: BTreeMap<String, Directory>,
+    files: BTreeMap<String, u64>,
+    size: u64,
+}
+
+/// Returns the absolute path of an entry inside the directory at `parent`.
+fn join_path(parent: &str, name: &str) -> String {
+    if parent.ends_with('/') {
+        format!("{}{}", parent, name)
+    } else {
+        format!("{}/{}", parent, name)
+    }
 }
 
 impl Directory {
     fn new() -> Directory {
         Directory {
-            sub_directories: HashMap::new(),
-            files: HashMap::new(),
+            sub_directories: BTreeMap::new(),
+            files: BTreeMap::new(),
+            size: 0,
         }
     }
 
-    fn add_file(&mut self, name: &str, size: u32) {
-        self.files.insert(name.to_string(), size);
+    /// Returns the directory at the given path, creating it and its parents if necessary.
+    fn create_dir_all(&mut self, path: &[String]) -> &mut Directory {
+        path.iter().fold(self, |dir, name| {
+            dir.sub_directories
+                .entry(name.clone())
+                .or_insert_with(Directory::new)
+        })
     }
 
-    fn add_sub_directory(&mut self, name: &str, directory: Directory) {
-        self.sub_directories.insert(name.to_string(), directory);
-    }
+    /// Adds a file to the directory at the given path, creating the directories if necessary.
+    fn add_file(&mut self, path: &[String], name: &str, size: u64) {
+        let old_size = self
+            .create_dir_all(path)
+            .files
+            .insert(name.to_string(), size)
+            .unwrap_or(0);
 
-    fn get_size(&self) -> u32 {
-        let mut size = 0;
-        for file_size in self.files.values() {
-            size += file_size;
+        let mut dir = self;
+        dir.size = dir.size - old_size + size;
+        for segment in path {
+            dir = dir.sub_directories.get_mut(segment).unwrap();
+            dir.size = dir.size - old_size + size;
         }
+    }
+
+    fn get_size(&self) -> u64 {
+        self.size
+    }
 
-        for dir in self.sub_directories.values() {
-            size += dir.get_size();
+    /// Looks up a directory by its absolute path like "/a/e", where this directory is "/".
+    fn get(&self, path: &str) -> Option<&Directory> {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |dir, name| dir.sub_directories.get(name))
+    }
+
+    /// Iterates over this directory and all directories below it with their absolute paths,
+    /// sorted by path.
+    fn walk(&self) -> Walk<'_> {
+        Walk {
+            stack: vec![("/".to_string(), self)],
         }
+    }
 
-        size
+    /// Iterates over all files below this directory with their absolute paths and sizes.
+    fn files(&self) -> impl Iterator<Item = (String, u64)> + '_ {
+        self.walk().flat_map(|(path, dir)| {
+            dir.files
+                .iter()
+                .map(move |(name, size)| (join_path(&path, name), *size))
+        })
+    }
+
+    /// Returns all directories with their total size like `du`, sorted by path.
+    fn du(&self) -> Vec<(String, u64)> {
+        self.walk()
+            .map(|(path, dir)| (path, dir.get_size()))
+            .collect()
+    }
+
+    /// Returns the files and directories whose absolute path matches the glob pattern with
+    /// their size. See `glob_matches` for the supported patterns.
+    fn glob(&self, pattern: &str) -> Vec<(String, u64)> {
+        let directories = self.walk().map(|(path, dir)| (path, dir.get_size()));
+        let mut matches: Vec<_> = directories
+            .chain(self.files())
+            .filter(|(path, _)| glob_matches(pattern, path))
+            .collect();
+        matches.sort();
+        matches
     }
 }
 
-fn parse_input(input: &PuzzleInput) -> Directory {
-    let steps: Vec<_> = input
-        .raw_input
-        .split('$')
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .collect();
-    let mut root_dir = Directory::new();
-    let mut cwd = vec![];
-
-    for step in steps {
-        let cmd = step.lines().next().unwrap();
-        let output = step
-            .lines()
-            .skip(1)
-            .filter(|line| !line.is_empty())
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let mut cmd_parts = cmd.split_whitespace();
-        match cmd_parts.next().unwrap() {
-            "cd" => {
-                let dir_name = cmd_parts.next().unwrap();
-                if dir_name == ".." {
-                    cwd.pop();
-                } else {
-                    let dir = Directory::new();
-
-                    let mut parent = &mut root_dir;
-                    for dir_name in &cwd {
-                        parent = parent.sub_directories.get_mut(*dir_name).unwrap();
-                    }
-                    parent.add_sub_directory(dir_name, dir);
+/// Depth-first iterator over a directory tree, see `Directory::walk`.
+struct Walk<'a> {
+    stack: Vec<(String, &'a Directory)>,
+}
 
-                    cwd.push(dir_name);
-                }
+impl<'a> Iterator for Walk<'a> {
+    type Item = (String, &'a Directory);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, dir) = self.stack.pop()?;
+        for (name, sub_dir) in dir.sub_directories.iter().rev() {
+            self.stack.push((join_path(&path, name), sub_dir));
+        }
+        Some((path, dir))
+    }
+}
+
+/// Matches an absolute path against a glob pattern, where `*` matches any characters within
+/// a path segment, `?` a single character and `**` any number of path segments.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn segments(s: &str) -> Vec<&str> {
+        s.split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+            Some((segment, rest)) => {
+                !path.is_empty()
+                    && match_segment(segment.as_bytes(), path[0].as_bytes())
+                    && match_segments(rest, &path[1..])
             }
-            "ls" => {
-                let mut parent = &mut root_dir;
-                for dir_name in &cwd {
-                    parent = parent.sub_directories.get_mut(*dir_name).unwrap();
-                }
+        }
+    }
 
-                for l in output.lines() {
-                    let size = l.split_whitespace().next().unwrap().parse::<u32>();
-                    if size.is_err() {
-                        continue;
-                    }
+    fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+        match (pattern.split_first(), name.split_first()) {
+            (None, None) => true,
+            (Some((b'*', rest)), _) => {
+                match_segment(rest, name)
+                    || (!name.is_empty() && match_segment(pattern, &name[1..]))
+            }
+            (Some((b'?', rest)), Some((_, name_rest))) => match_segment(rest, name_rest),
+            (Some((p, rest)), Some((n, name_rest))) if p == n => match_segment(rest, name_rest),
+            _ => false,
+        }
+    }
+
+    match_segments(&segments(pattern), &segments(path))
+}
+
+/// Resolves the argument of `cd` against the current directory. Absolute paths start at the
+/// root, `.` and `..` work like in a shell and every directory on the way has to exist.
+fn change_directory(
+    root: &Directory,
+    cwd: &[String],
+    path: &str,
+) -> Result<Vec<String>, SolveError> {
+    let mut target = if path.starts_with('/') {
+        vec![]
+    } else {
+        cwd.to_vec()
+    };
 
-                    let name = l.split_whitespace().skip(1).collect::<Vec<_>>().join(" ");
-                    parent.add_file(&name, size.unwrap());
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        match segment {
+            "." => {}
+            ".." => {
+                target.pop();
+            }
+            name => {
+                target.push(name.to_string());
+                if root.get(&target.join("/")).is_none() {
+                    return Err(SolveError::new(format!(
+                        "No such directory: /{}",
+                        target.join("/")
+                    )));
                 }
             }
-            _ => panic!("Unknown command: {}", cmd),
         }
     }
 
-    root_dir
+    Ok(target)
 }
 
-fn get_directory_sizes(dir: &Directory) -> Vec<u32> {
-    let mut sizes = vec![];
-    sizes.push(dir.get_size());
+/// Checks that a name listed by `ls` can be used as a single path segment.
+fn validate_name(name: &str) -> Result<&str, SolveError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        Err(SolveError::new(format!("Invalid file name: {:?}", name)))
+    } else {
+        Ok(name)
+    }
+}
+
+/// Replays the terminal output and reconstructs the filesystem from the listed directories
+/// and files.
+fn parse_input(input: &PuzzleInput) -> Result<Directory, SolveError> {
+    let mut root_dir = Directory::new();
+    let mut cwd: Vec<String> = vec![];
+
+    for (number, line) in input.numbered_lines() {
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(cmd) = line.strip_prefix('$') {
+            let cmd_parts: Vec<&str> = cmd.split_whitespace().collect();
+            match cmd_parts[..] {
+                ["cd", path] => {
+                    cwd = change_directory(&root_dir, &cwd, path)
+                        .map_err(|e| e.at_line(number, line))?;
+                }
+                ["ls"] => {}
+                _ => {
+                    return Err(SolveError::new(format!("Unknown command: {}", cmd.trim()))
+                        .at_line(number, line))
+                }
+            }
+        } else {
+            // output of ls
+            let (size, name) = line.split_once(' ').ok_or_else(|| {
+                SolveError::new("Expected ls output like \"<size> <name>\"").at_line(number, line)
+            })?;
+            let name = validate_name(name).map_err(|e| e.at_line(number, line))?;
 
-    for (_, sub_dir) in dir.sub_directories.iter() {
-        sizes.extend(get_directory_sizes(sub_dir));
+            if size == "dir" {
+                cwd.push(name.to_string());
+                root_dir.create_dir_all(&cwd);
+                cwd.pop();
+                continue;
+            }
+
+            // sizes of single files fit into u32, so the u64 totals can't overflow
+            let size = size
+                .parse::<u32>()
+                .map_err(|e| SolveError::from(e).at_line(number, line))?;
+            root_dir.add_file(&cwd, name, size as u64);
+        }
     }
 
-    sizes
+    Ok(root_dir)
 }
 
-fn solve_a(input: &PuzzleInput) -> u32 {
-    let root = parse_input(input);
+fn solve_a(input: &PuzzleInput) -> Result<u64, SolveError> {
+    let root = parse_input(input)?;
 
-    let dirs = get_directory_sizes(&root);
+    Ok(root
+        .walk()
+        .map(|(_, dir)| dir.get_size())
+        .filter(|size| *size <= 100000)
+        .sum())
+}
 
-    dirs.iter().filter(|dir| **dir <= 100000).sum()
+/// Formats a size either in bytes or, if `human` is set, with binary units like "92.6K".
+fn format_size(size: u64, human: bool) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if !human || size < 1024 {
+        return size.to_string();
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
 }
 
-fn solve_b(input: &PuzzleInput) -> u32 {
-    let root = parse_input(input);
-    let mut dirs = get_directory_sizes(&root);
-    dirs.sort();
-    let current_size = *dirs.iter().max().unwrap();
-    let needed_size = 30000000;
-    let size_total = 70000000;
+/// Renders the directory like `tree`, with the entries of every directory sorted by name and
+/// the size of every file and directory.
+fn render_tree(root: &Directory, human: bool) -> String {
+    fn render(dir: &Directory, prefix: &str, human: bool, output: &mut String) {
+        let directories = dir
+            .sub_directories
+            .iter()
+            .map(|(name, d)| (name, Some(d), d.get_size()));
+        let files = dir.files.iter().map(|(name, size)| (name, None, *size));
+        let mut entries: Vec<_> = directories.chain(files).collect();
+        entries.sort_by_key(|(name, _, _)| *name);
+
+        for (i, (name, sub_dir, size)) in entries.iter().enumerate() {
+            let last = i == entries.len() - 1;
+            let connector = if last { "└── " } else { "├── " };
+            let suffix = if sub_dir.is_some() { "/" } else { "" };
+            output.push_str(&format!(
+                "{}{}{}{} ({})\n",
+                prefix,
+                connector,
+                name,
+                suffix,
+                format_size(*size, human)
+            ));
 
-    for dir_size in dirs {
-        if current_size - dir_size + needed_size <= size_total {
-            return dir_size;
+            if let Some(sub_dir) = sub_dir {
+                let indent = if last { "    " } else { "│   " };
+                render(sub_dir, &format!("{}{}", prefix, indent), human, output);
+            }
         }
     }
 
-    0
+    let mut output = format!("/ ({})\n", format_size(root.get_size(), human));
+    render(root, "", human, &mut output);
+    output
+}
+
+/// Returns the `n` largest directories, largest first.
+fn largest_directories(root: &Directory, n: usize) -> Vec<(String, u64)> {
+    let mut dirs = root.du();
+    dirs.sort_by(|(path_a, size_a), (path_b, size_b)| size_b.cmp(size_a).then(path_a.cmp(path_b)));
+    dirs.truncate(n);
+    dirs
+}
+
+/// Returns the directories that free up enough space for the update when deleted, smallest
+/// first.
+fn deletion_candidates(root: &Directory, disk_size: u64, needed_space: u64) -> Vec<(String, u64)> {
+    // also correct for an over-full disk, where the used space exceeds the disk size
+    let missing = root.get_size().saturating_add(needed_space).saturating_sub(disk_size);
+
+    let mut candidates: Vec<_> = root
+        .du()
+        .into_iter()
+        .filter(|(_, size)| *size >= missing)
+        .collect();
+    candidates.sort_by_key(|(path, size)| (*size, path.clone()));
+    candidates
+}
+
+fn solve_b(input: &PuzzleInput) -> Result<u64, SolveError> {
+    let root = parse_input(input)?;
+
+    match deletion_candidates(&root, DISK_SIZE, NEEDED_SPACE).first() {
+        Some((_, size)) => Ok(*size),
+        None => Err(SolveError::new(
+            "No directory is large enough to free up the needed space",
+        )),
+    }
 }
 
 #[cfg(test)]
@@ -171,17 +472,109 @@ $ ls
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 95437);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 95437);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 24933642);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 24933642);
+    }
+
+    #[test]
+    fn test_path_queries() {
+        let root = parse_input(&PuzzleInput::new(TEST_INPUT)).unwrap();
+        assert_eq!(root.get("/a/e").unwrap().get_size(), 584);
+        assert!(root.get("/a/x").is_none());
+
+        let files: Vec<_> = root.files().map(|(path, _)| path).collect();
+        assert!(files.contains(&"/a/e/i".to_string()));
+        assert_eq!(files.len(), 10);
+
+        let logs: Vec<_> = root
+            .glob("/**/*.l?g")
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(logs, vec!["/d/d.log"]);
+        assert_eq!(root.glob("/*/e").len(), 1);
+        assert_eq!(root.du()[0], ("/".to_string(), 48381165));
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let input = "$ cd /
+$ ls
+dir a
+dir empty
+$ cd a
+$ ls
+dir b
+$ cd b/./../b
+$ ls
+10 x
+$ cd /a
+$ ls
+20 y
+$ cd ../empty/..
+$ cd /
+$ ls
+30 z";
+        let root = parse_input(&PuzzleInput::new(input)).unwrap();
+        assert_eq!(
+            root.du(),
+            vec![
+                ("/".to_string(), 60),
+                ("/a".to_string(), 30),
+                ("/a/b".to_string(), 10),
+                ("/empty".to_string(), 0)
+            ]
+        );
+
+        let error = parse_input(&PuzzleInput::new("$ cd /\n$ ls\ndir a\n$ cd a/c")).unwrap_err();
+        assert_eq!(error.line_number(), Some(4));
+        assert_eq!(error.message(), "No such directory: /a/c");
+        assert!(parse_input(&PuzzleInput::new("$ cd b")).is_err());
+        assert!(parse_input(&PuzzleInput::new("$ ls\n1 a/b")).is_err());
+    }
+
+    #[test]
+    fn test_reports() {
+        let root = parse_input(&PuzzleInput::new(TEST_INPUT)).unwrap();
+
+        let tree = render_tree(&root, true);
+        assert!(
+            tree.starts_with("/ (46.1M)\n├── a/ (92.6K)\n│   ├── e/ (584)\n│   │   └── i (584)\n")
+        );
+        assert!(tree.ends_with("    └── k (6.9M)\n"));
+        assert_eq!(tree.lines().count(), 14);
+
+        assert_eq!(
+            largest_directories(&root, 2),
+            vec![("/".to_string(), 48381165), ("/d".to_string(), 24933642)]
+        );
+        assert_eq!(
+            deletion_candidates(&root, DISK_SIZE, NEEDED_SPACE),
+            vec![("/d".to_string(), 24933642), ("/".to_string(), 48381165)]
+        );
+        assert_eq!(deletion_candidates(&root, DISK_SIZE, 1000).len(), 4);
+
+        // 48381165 bytes are used on a 40000000 byte disk, so 28381165 bytes have to be
+        // deleted to free up 20000000
+        assert_eq!(
+            deletion_candidates(&root, 40_000_000, 20_000_000),
+            vec![("/".to_string(), 48381165)]
+        );
+        assert!(deletion_candidates(&root, 40_000_000, 40_000_001).is_empty());
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
@@ -1,24 +1,122 @@
-use aoc_utils::PuzzleInput;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use aoc_utils::{exit_on_error, print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 8;
 
+/// The per-tree results that can be drawn.
+#[derive(Clone, Copy)]
+enum MapKind {
+    Visibility,
+    Scenic,
+}
+
 fn main() {
+    let usage = || -> ! {
+        eprintln!(
+            "Usage: day08 [--map <visibility|scenic> [--ppm <file> | --png <file>] [--scale <n>]]"
+        );
+        std::process::exit(2);
+    };
+
+    let mut map = None;
+    let mut output = None;
+    let mut scale = 1;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--map" => {
+                map = match args.next().as_deref() {
+                    Some("visibility") => Some(MapKind::Visibility),
+                    Some("scenic") => Some(MapKind::Scenic),
+                    _ => usage(),
+                }
+            }
+            "--ppm" | "--png" => {
+                let path = args.next().unwrap_or_else(|| usage());
+                if output.replace((arg, path)).is_some() {
+                    usage();
+                }
+            }
+            "--scale" => {
+                scale = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if n > 0 => n,
+                    _ => usage(),
+                }
+            }
+            _ => usage(),
+        }
+    }
+
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    let map = match map {
+        Some(map) => map,
+        None if output.is_none() && scale == 1 => {
+            print_answer("A", solve_a(&input));
+            print_answer("B", solve_b(&input));
+            return;
+        }
+        None => usage(),
+    };
+
+    let tree_map = exit_on_error(parse_input(&input));
+    let views = compute_views(&tree_map);
+    let image = match map {
+        MapKind::Visibility => Image::visibility(&tree_map, &views),
+        MapKind::Scenic => Image::scenic(&views),
+    };
+
+    let result = match output {
+        None => {
+            print!("{}", image.to_ansi(&tree_map));
+            Ok(())
+        }
+        Some((format, path)) => File::create(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                let image = image.scaled(scale);
+                let mut writer = BufWriter::new(file);
+                let written = if format == "--ppm" {
+                    writer.write_all(&image.to_ppm()).map_err(|e| e.to_string())
+                } else {
+                    image.write_png(&mut writer).map_err(|e| e.to_string())
+                };
+                written.and_then(|_| writer.flush().map_err(|e| e.to_string()))
+            })
+            .map_err(|e| format!("Unable to write {}: {}", path, e)),
+    };
+
+    exit_on_error(result);
 }
 
-fn parse_input(input: &PuzzleInput) -> Vec<Vec<u8>> {
-    input
-        .lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| c.to_string().parse().unwrap())
-                .collect()
-        })
-        .collect()
+fn parse_input(input: &PuzzleInput) -> Result<Vec<Vec<u8>>, SolveError> {
+    let tree_map: Vec<Vec<u8>> = input.parse_lines(|line| {
+        line.chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .map(|d| d as u8)
+                    .ok_or_else(|| SolveError::new(format!("Invalid tree height: {}", c)))
+            })
+            .collect()
+    })?;
+
+    let width = tree_map.first().map_or(0, Vec::len);
+    if width == 0 {
+        return Err(SolveError::new("Tree map is empty"));
+    }
+
+    for (number, line) in input.numbered_lines() {
+        if tree_map[number - 1].len() != width {
+            return Err(SolveError::new(format!("Expected {} trees in this row", width))
+                .at_line(number, line));
+        }
+    }
+
+    Ok(tree_map)
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     Left,
     Right,
@@ -26,78 +124,242 @@ enum Direction {
     Down,
 }
 
-fn view_distance(tree_map: &[Vec<u8>], x: usize, y: usize, d: Direction) -> (usize, bool) {
-    let height = tree_map[y][x];
-    let mut trees_in_line: Vec<_> = match d {
-        Direction::Left => tree_map[y].iter().take(x).collect(),
-        Direction::Right => tree_map[y].iter().skip(x + 1).collect(),
-        Direction::Up => tree_map.iter().take(y).map(|v| &v[x]).collect(),
-        Direction::Down => tree_map.iter().skip(y + 1).map(|v| &v[x]).collect(),
-    };
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ];
 
-    if d == Direction::Left || d == Direction::Up {
-        trees_in_line.reverse();
+    /// Returns the number of lines and the number of trees per line when looking in this
+    /// direction: rows for left and right, columns for up and down.
+    fn lines(&self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Direction::Left | Direction::Right => (height, width),
+            Direction::Up | Direction::Down => (width, height),
+        }
     }
 
-    let mut view_distance = trees_in_line.iter().take_while(|v| ***v < height).count();
-    let can_see_edge = view_distance == trees_in_line.len();
-    if !can_see_edge {
-        view_distance += 1;
+    /// Returns the coordinates of the `i`th tree of a line, ordered so that looking in this
+    /// direction from a tree means looking back towards the start of its line.
+    fn position(&self, line: usize, i: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Direction::Left => (i, line),
+            Direction::Right => (width - 1 - i, line),
+            Direction::Up => (line, i),
+            Direction::Down => (line, height - 1 - i),
+        }
     }
+}
 
-    (view_distance, can_see_edge)
+/// Visibility from outside the forest and scenic score of every tree, indexed like the
+/// height map as `[y][x]`.
+#[derive(Debug, PartialEq)]
+struct Views {
+    visible: Vec<Vec<bool>>,
+    scenic_scores: Vec<Vec<usize>>,
 }
 
-fn is_visible(tree_map: &[Vec<u8>], x: usize, y: usize) -> bool {
-    let (_, left) = view_distance(tree_map, x, y, Direction::Left);
-    let (_, right) = view_distance(tree_map, x, y, Direction::Right);
-    let (_, top) = view_distance(tree_map, x, y, Direction::Up);
-    let (_, bottom) = view_distance(tree_map, x, y, Direction::Down);
+/// Computes the views of all trees with one sweep over the map per direction.
+///
+/// Each sweep keeps a stack of the trees that no later tree is taller than, so the heights on
+/// the stack never increase. Trees of equal height both stay on it, as either one blocks the
+/// view of the trees behind. The tree blocking the view of the current tree is the top of the
+/// stack after popping all smaller trees. Every tree is pushed and popped at most once per
+/// direction, so this takes linear time.
+fn compute_views(tree_map: &[Vec<u8>]) -> Views {
+    let height = tree_map.len();
+    let width = tree_map[0].len();
+    let mut views = Views {
+        visible: vec![vec![false; width]; height],
+        scenic_scores: vec![vec![1; width]; height],
+    };
+
+    for direction in Direction::ALL {
+        let (lines, line_length) = direction.lines(width, height);
+        let mut stack: Vec<(usize, u8)> = Vec::with_capacity(line_length);
+
+        for line in 0..lines {
+            stack.clear();
+
+            for i in 0..line_length {
+                let (x, y) = direction.position(line, i, width, height);
+                let tree_height = tree_map[y][x];
+                while stack.last().is_some_and(|&(_, h)| h < tree_height) {
+                    stack.pop();
+                }
 
-    left || right || top || bottom
+                let view_distance = match stack.last() {
+                    Some(&(blocking, _)) => i - blocking,
+                    None => {
+                        views.visible[y][x] = true;
+                        i
+                    }
+                };
+                views.scenic_scores[y][x] *= view_distance;
+                stack.push((i, tree_height));
+            }
+        }
+    }
+
+    views
+}
+
+/// An RGB image with one pixel per tree, or `scale` x `scale` pixels after `Image::scaled`.
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    let tree_map = parse_input(input);
+impl Image {
+    /// Visible trees are green and hidden trees are red, both brighter the taller the tree.
+    fn visibility(tree_map: &[Vec<u8>], views: &Views) -> Image {
+        let pixels = tree_map
+            .iter()
+            .zip(&views.visible)
+            .flat_map(|(heights, visible)| heights.iter().zip(visible))
+            .map(|(&height, &visible)| {
+                let brightness = 75 + 20 * height;
+                if visible {
+                    [0, brightness, 0]
+                } else {
+                    [brightness, 0, 0]
+                }
+            })
+            .collect();
+
+        Image {
+            width: tree_map[0].len(),
+            height: tree_map.len(),
+            pixels,
+        }
+    }
+
+    /// Scenic scores relative to the best one, from blue over red to yellow.
+    fn scenic(views: &Views) -> Image {
+        let max_score = views.scenic_scores.iter().flatten().max().copied().unwrap_or(0);
+        let pixels = views
+            .scenic_scores
+            .iter()
+            .flatten()
+            .map(|&score| {
+                // square root to spread out the many low scores
+                let level = (score as f64 / max_score.max(1) as f64).sqrt() * 510.0;
+                let level = level.round() as u32;
+                [
+                    level.min(255) as u8,
+                    level.saturating_sub(255) as u8,
+                    160 - level.min(160) as u8,
+                ]
+            })
+            .collect();
+
+        Image {
+            width: views.scenic_scores[0].len(),
+            height: views.scenic_scores.len(),
+            pixels,
+        }
+    }
+
+    /// Returns the image with every pixel enlarged to a `scale` x `scale` square.
+    fn scaled(&self, scale: usize) -> Image {
+        let pixels = (0..self.height * scale)
+            .flat_map(|y| {
+                let row = y / scale * self.width;
+                (0..self.width * scale).map(move |x| self.pixels[row + x / scale])
+            })
+            .collect();
 
-    let outer_visible = 2 * tree_map.len() + 2 * tree_map[0].len() - 4;
-    let inner_visible: usize = (1..tree_map.len() - 1)
-        .map(|y| {
-            (1..tree_map[0].len() - 1)
-                .filter(|x| is_visible(&tree_map, *x, y))
-                .count()
-        })
-        .sum();
+        Image {
+            width: self.width * scale,
+            height: self.height * scale,
+            pixels,
+        }
+    }
+
+    /// Encodes the image as binary PPM (P6).
+    fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(self.pixels.iter().flatten());
+        ppm
+    }
 
-    inner_visible + outer_visible
+    fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let data: Vec<u8> = self.pixels.iter().flatten().copied().collect();
+        encoder.write_header()?.write_image_data(&data)
+    }
+
+    /// Prints the height of every tree coloured like its pixel using ANSI true colour codes.
+    fn to_ansi(&self, tree_map: &[Vec<u8>]) -> String {
+        let mut output = String::new();
+        for (row, heights) in self.pixels.chunks(self.width).zip(tree_map) {
+            for ([r, g, b], height) in row.iter().zip(heights) {
+                output.push_str(&format!("\x1b[1;38;2;{};{};{}m{}", r, g, b, height));
+            }
+            output.push_str("\x1b[0m\n");
+        }
+        output
+    }
 }
 
-fn calculate_scenic_core(tree_map: &[Vec<u8>], x: usize, y: usize) -> usize {
-    let (left_view, _) = view_distance(tree_map, x, y, Direction::Left);
-    let (right_view, _) = view_distance(tree_map, x, y, Direction::Right);
-    let (top_view, _) = view_distance(tree_map, x, y, Direction::Up);
-    let (bottom_view, _) = view_distance(tree_map, x, y, Direction::Down);
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    let tree_map = parse_input(input)?;
+    let views = compute_views(&tree_map);
 
-    left_view * right_view * top_view * bottom_view
+    Ok(views.visible.iter().flatten().filter(|v| **v).count())
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    let tree_map = parse_input(input);
-
-    (1..tree_map.len() - 1)
-        .map(|y| {
-            (1..tree_map[0].len() - 1)
-                .map(|x| calculate_scenic_core(&tree_map, x, y))
-                .max()
-                .unwrap()
-        })
-        .max()
-        .unwrap()
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    let tree_map = parse_input(input)?;
+    let views = compute_views(&tree_map);
+
+    Ok(views.scenic_scores.into_iter().flatten().max().unwrap_or(0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_utils::check::assert_agree;
+    use aoc_utils::generate;
+
+    /// Straightforward reference that walks from every tree in every direction.
+    fn naive_views(tree_map: &[Vec<u8>]) -> Views {
+        let height = tree_map.len();
+        let width = tree_map[0].len();
+        let mut views = Views {
+            visible: vec![vec![false; width]; height],
+            scenic_scores: vec![vec![1; width]; height],
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let lines: [Vec<u8>; 4] = [
+                    (0..x).rev().map(|i| tree_map[y][i]).collect(),
+                    (x + 1..width).map(|i| tree_map[y][i]).collect(),
+                    (0..y).rev().map(|i| tree_map[i][x]).collect(),
+                    (y + 1..height).map(|i| tree_map[i][x]).collect(),
+                ];
+
+                for line in lines {
+                    let smaller = line.iter().take_while(|h| **h < tree_map[y][x]).count();
+                    if smaller == line.len() {
+                        views.visible[y][x] = true;
+                        views.scenic_scores[y][x] *= smaller;
+                    } else {
+                        views.scenic_scores[y][x] *= smaller + 1;
+                    }
+                }
+            }
+        }
+
+        views
+    }
 
     const TEST_INPUT: &str = "30373
 25512
@@ -108,17 +370,72 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 21);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 21);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 8);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_compute_views() {
+        let tree_map = parse_input(&PuzzleInput::new(TEST_INPUT)).unwrap();
+        let views = compute_views(&tree_map);
+        assert_eq!(views.visible[1], vec![true, true, true, false, true]);
+        assert_eq!(views.scenic_scores[3], vec![0, 1, 8, 3, 0]);
+
+        let single = parse_input(&PuzzleInput::new("5")).unwrap();
+        assert_eq!(compute_views(&single).visible, vec![vec![true]]);
+    }
+
+    #[test]
+    fn test_compute_views_agrees_with_naive() {
+        assert_agree(
+            |rng| generate::tree_map(rng, 12),
+            |input| naive_views(&parse_input(input).unwrap()),
+            |input| compute_views(&parse_input(input).unwrap()),
+        );
+    }
+
+    #[test]
+    fn test_images() {
+        let tree_map = parse_input(&PuzzleInput::new(TEST_INPUT)).unwrap();
+        let views = compute_views(&tree_map);
+
+        let visibility = Image::visibility(&tree_map, &views);
+        // both 5s in the second row are visible, the 1 next to them is hidden
+        assert_eq!(visibility.pixels[5 + 1], [0, 175, 0]);
+        assert_eq!(visibility.pixels[5 + 2], [0, 175, 0]);
+        assert_eq!(visibility.pixels[5 + 3], [95, 0, 0]);
+
+        let scenic = Image::scenic(&views);
+        assert_eq!(scenic.pixels[0], [0, 0, 160]);
+        assert_eq!(scenic.pixels[3 * 5 + 2], [255, 255, 0]);
+
+        let ppm = visibility.scaled(2).to_ppm();
+        assert!(ppm.starts_with(b"P6\n10 10\n255\n"));
+        assert_eq!(ppm.len(), 13 + 10 * 10 * 3);
+
+        let mut png_data = Vec::new();
+        scenic.write_png(&mut png_data).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(png_data));
+        let info = decoder.read_info().unwrap();
+        assert_eq!((info.info().width, info.info().height), (5, 5));
+
+        let ansi = visibility.to_ansi(&tree_map);
+        assert_eq!(ansi.lines().count(), 5);
+        assert!(ansi.starts_with("\x1b[1;38;2;0;135;0m3"));
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
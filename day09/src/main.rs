@@ -1,12 +1,84 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
 
-use aoc_utils::PuzzleInput;
+use aoc_utils::{exit_on_error, print_answer, write_frames, PuzzleInput, SolveError};
 const DAY: u8 = 9;
 
 fn main() {
+    let usage = || -> ! {
+        eprintln!(
+            "Usage: day09 [--knots <n>] [--trace [--frames <directory> | --animate [--delay <ms>]]]"
+        );
+        std::process::exit(2);
+    };
+    let number = |arg: Option<String>| -> u64 {
+        match arg.and_then(|n| n.parse().ok()) {
+            Some(n) if n > 0 => n,
+            _ => usage(),
+        }
+    };
+
+    let mut knots = None;
+    let mut trace_steps = false;
+    let mut frames_directory = None;
+    let mut animate = false;
+    let mut delay = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--knots" => knots = Some(number(args.next()) as usize),
+            "--trace" => trace_steps = true,
+            "--frames" => frames_directory = Some(args.next().unwrap_or_else(|| usage())),
+            "--animate" => animate = true,
+            "--delay" => delay = Some(number(args.next())),
+            _ => usage(),
+        }
+    }
+    let output_options = frames_directory.is_some() || animate || delay.is_some();
+    if (output_options && !trace_steps)
+        || (frames_directory.is_some() && animate)
+        || (delay.is_some() && !animate)
+    {
+        usage();
+    }
+
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    if !trace_steps {
+        match knots {
+            None => {
+                print_answer("A", solve_a(&input));
+                print_answer("B", solve_b(&input));
+            }
+            Some(knots) => {
+                print_answer(&format!("{} knots", knots), count_tail_positions(&input, knots))
+            }
+        }
+        return;
+    }
+
+    let result = trace(&input, knots.unwrap_or(10)).and_then(|frames| {
+        if let Some(directory) = &frames_directory {
+            write_frames(&frames, Path::new(directory))
+        } else if animate {
+            let delay = Duration::from_millis(delay.unwrap_or(200));
+            for frame in frames {
+                // clear the terminal and move the cursor to the top left corner
+                print!("\x1b[2J\x1b[H{}", frame);
+                std::io::stdout().flush().ok();
+                std::thread::sleep(delay);
+            }
+            Ok(())
+        } else {
+            println!("{}", frames.join("\n"));
+            Ok(())
+        }
+    });
+
+    exit_on_error(result);
 }
 
 #[derive(Copy, Clone)]
@@ -17,14 +89,16 @@ enum Direction {
     Left,
 }
 
-impl From<char> for Direction {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Direction {
+    type Error = SolveError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            'U' => Direction::Up,
-            'D' => Direction::Down,
-            'R' => Direction::Right,
-            'L' => Direction::Left,
-            _ => panic!("Invalid direction: {}", c),
+            'U' => Ok(Direction::Up),
+            'D' => Ok(Direction::Down),
+            'R' => Ok(Direction::Right),
+            'L' => Ok(Direction::Left),
+            _ => Err(SolveError::new(format!("Invalid direction: {}", c))),
         }
     }
 }
@@ -35,46 +109,76 @@ struct Command {
     distance: usize,
 }
 
-impl From<String> for Command {
-    fn from(s: String) -> Self {
-        let direction = Direction::from(s.chars().next().unwrap());
-        let distance = s[1..].trim().parse().unwrap();
-        Command {
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Right => 'R',
+            Direction::Left => 'L',
+        };
+        write!(f, "{} {}", direction, self.distance)
+    }
+}
+
+impl TryFrom<&str> for Command {
+    type Error = SolveError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let (direction, distance) = s
+            .split_once(' ')
+            .ok_or_else(|| SolveError::new("Expected command like \"R 4\""))?;
+
+        let mut direction_chars = direction.chars();
+        let direction = match (direction_chars.next(), direction_chars.next()) {
+            (Some(c), None) => Direction::try_from(c)?,
+            _ => return Err(SolveError::new(format!("Invalid direction: {}", direction))),
+        };
+        let distance = distance.trim().parse()?;
+
+        Ok(Command {
             direction,
             distance,
-        }
+        })
     }
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 struct Position(isize, isize);
 
-struct GameState {
-    head_position: Position,
-    knot_positions: [Position; 9],
-    commands: Vec<Command>,
-    visited_knot_positions: [HashSet<Position>; 9],
+fn parse_commands(input: &PuzzleInput) -> Result<Vec<Command>, SolveError> {
+    input.parse_lines(|line| Command::try_from(line))
 }
 
-impl GameState {
-    fn parse(input: &PuzzleInput) -> Self {
-        let commands = input
-            .lines()
-            .map(Command::from)
-            .collect();
+/// A rope of knots, where the first knot is the head and every other knot follows the knot
+/// before it. The visited positions are only recorded for the knots that are tracked.
+struct Rope {
+    knots: Vec<Position>,
+    visited: BTreeMap<usize, HashSet<Position>>,
+}
 
-        let mut visited_knot_positions = vec![];
-        for _ in 0..9 {
-            visited_knot_positions.push(HashSet::new());
-            visited_knot_positions.last_mut().unwrap().insert(Position(0, 0));
+impl Rope {
+    fn new(knot_count: usize) -> Rope {
+        Rope {
+            knots: vec![Position(0, 0); knot_count.max(1)],
+            visited: BTreeMap::new(),
         }
+    }
 
-        GameState {
-            head_position: Position(0, 0),
-            knot_positions: [Position(0, 0); 9],
-            commands,
-            visited_knot_positions: visited_knot_positions.try_into().unwrap(),
-        }
+    /// Starts recording the positions visited by the knot, including its current one.
+    fn track(mut self, knot_index: usize) -> Rope {
+        let position = self.knots[knot_index];
+        self.visited.entry(knot_index).or_default().insert(position);
+        self
+    }
+
+    fn tail_index(&self) -> usize {
+        self.knots.len() - 1
+    }
+
+    /// Returns the positions visited by the knot, or `None` if it is not tracked.
+    fn visited(&self, knot_index: usize) -> Option<&HashSet<Position>> {
+        self.visited.get(&knot_index)
     }
 
     fn update_head(&mut self, direction: &Direction) {
@@ -85,75 +189,31 @@ impl GameState {
             Direction::Left => (-1, 0),
         };
 
-        self.head_position.0 += dx;
-        self.head_position.1 += dy;
+        self.knots[0].0 += dx;
+        self.knots[0].1 += dy;
     }
 
+    /// Moves a knot one step towards the knot before it unless they are touching, which
+    /// covers straight and diagonal moves alike.
     fn update_knot(&mut self, knot_index: usize) {
-        let head_knot = if knot_index == 0 {self.head_position} else {self.knot_positions[knot_index - 1]};
-        let tail_knot = self.knot_positions[knot_index];
+        let head = self.knots[knot_index - 1];
+        let knot = &mut self.knots[knot_index];
+        let (dx, dy) = (head.0 - knot.0, head.1 - knot.1);
 
-        let (tx, ty) = (tail_knot.0, tail_knot.1);
-        let (hx, hy) = (head_knot.0, head_knot.1);
-
-        // if tail is adjacent (even diagonally) to head or on head, don't move
-        if (tx - hx).abs() <= 1 && (ty - hy).abs() <= 1 {
-            return;
+        if dx.abs() > 1 || dy.abs() > 1 {
+            knot.0 += dx.signum();
+            knot.1 += dy.signum();
         }
-
-        let (dx, dy) = if tx == hx || ty == hy {
-            // straight line
-            if tx == hx {
-                // vertical line
-                if ty < hy {
-                    // tail is above head
-                    (0, 1)
-                } else {
-                    // tail is below head
-                    (0, -1)
-                }
-            } else {
-                // horizontal line
-                if tx < hx {
-                    // tail is left of head
-                    (1, 0)
-                } else {
-                    // tail is right of head
-                    (-1, 0)
-                }
-            }
-        } else {
-            // Diagonal
-            if tx < hx {
-                // tail is left of head
-                if ty < hy {
-                    // tail is above head
-                    (1, 1)
-                } else {
-                    // tail is below head
-                    (1, -1)
-                }
-            } else {
-                // tail is right of head
-                if ty < hy {
-                    // tail is above head
-                    (-1, 1)
-                } else {
-                    // tail is below head
-                    (-1, -1)
-                }
-            }
-        };
-
-        self.knot_positions[knot_index].0 += dx;
-        self.knot_positions[knot_index].1 += dy;
     }
 
     fn execute_step(&mut self, direction: &Direction) {
         self.update_head(direction);
-        for knot_index in 0..9 {
+        for knot_index in 1..self.knots.len() {
             self.update_knot(knot_index);
-            self.visited_knot_positions[knot_index].insert(self.knot_positions[knot_index]);
+        }
+
+        for (knot_index, visited) in &mut self.visited {
+            visited.insert(self.knots[*knot_index]);
         }
     }
 
@@ -162,26 +222,84 @@ impl GameState {
             self.execute_step(&command.direction);
         }
     }
+}
 
-    fn simulate(&mut self) {
-        for command in &self.commands.to_vec() {
-            self.execute_command(command);
+/// Returns the symbol of a knot in a drawing: `H` for the head and the knot number for the
+/// others, continuing with letters after 9.
+fn knot_symbol(knot_index: usize) -> char {
+    match knot_index {
+        0 => 'H',
+        _ => std::char::from_digit(knot_index as u32, 36).unwrap_or('*'),
+    }
+}
+
+impl Rope {
+    /// Draws the rope like the puzzle's diagrams, with the top row being the largest y.
+    /// Knots cover the knots behind them, `s` marks the start and `#` the cells visited by
+    /// the tracked knots. The grid grows to fit everything drawn.
+    fn render(&self) -> String {
+        let start = Position(0, 0);
+        let visited: HashSet<Position> = self.visited.values().flatten().copied().collect();
+        let positions = || self.knots.iter().chain(&visited).chain([&start]);
+        let min_x = positions().map(|p| p.0).min().unwrap();
+        let max_x = positions().map(|p| p.0).max().unwrap();
+        let min_y = positions().map(|p| p.1).min().unwrap();
+        let max_y = positions().map(|p| p.1).max().unwrap();
+
+        let mut drawing = String::new();
+        for y in (min_y..=max_y).rev() {
+            for x in min_x..=max_x {
+                let position = Position(x, y);
+                let symbol = match self.knots.iter().position(|k| *k == position) {
+                    Some(knot_index) => knot_symbol(knot_index),
+                    None if position == start => 's',
+                    None if visited.contains(&position) => '#',
+                    None => '.',
+                };
+                drawing.push(symbol);
+            }
+            drawing.push('\n');
         }
+        drawing
+    }
+}
+
+/// Executes the commands with a rope of the given length, tracking its tail, and returns a
+/// frame for the initial rope and one after every command.
+fn trace(input: &PuzzleInput, knot_count: usize) -> Result<Vec<String>, SolveError> {
+    let commands = parse_commands(input)?;
+    let rope = Rope::new(knot_count);
+    let tail = rope.tail_index();
+    let mut rope = rope.track(tail);
+
+    let mut frames = vec![format!("== initial ==\n\n{}", rope.render())];
+    for command in &commands {
+        rope.execute_command(command);
+        frames.push(format!("== {} ==\n\n{}", command, rope.render()));
     }
+
+    Ok(frames)
+}
+
+/// Counts the positions visited by the tail of a rope with the given number of knots.
+fn count_tail_positions(input: &PuzzleInput, knot_count: usize) -> Result<usize, SolveError> {
+    let commands = parse_commands(input)?;
+    let rope = Rope::new(knot_count);
+    let tail = rope.tail_index();
+    let mut rope = rope.track(tail);
+
+    for command in &commands {
+        rope.execute_command(command);
+    }
+    Ok(rope.visited(tail).map_or(0, HashSet::len))
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    let mut state = GameState::parse(input);
-    
-    state.simulate();
-    state.visited_knot_positions[0].len()
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    count_tail_positions(input, 2)
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    let mut state = GameState::parse(input);
-    
-    state.simulate();
-    state.visited_knot_positions[8].len()
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    count_tail_positions(input, 10)
 }
 
 #[cfg(test)]
@@ -191,8 +309,8 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
@@ -204,7 +322,7 @@ D 1
 R 4
 D 1
 L 5
-R 2")), 13);
+R 2")).unwrap(), 13);
     }
 
     #[test]
@@ -216,6 +334,48 @@ D 3
 R 17
 D 10
 L 25
-U 20")), 36);
+U 20")).unwrap(), 36);
+    }
+
+    #[test]
+    fn test_rope() {
+        let input = PuzzleInput::new("R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2");
+        let commands = parse_commands(&input).unwrap();
+
+        // the knots only follow the knots before them, so the first knots of a long rope
+        // move exactly like a shorter rope
+        let mut rope = Rope::new(10).track(0).track(1).track(9);
+        for command in &commands {
+            rope.execute_command(command);
+        }
+        assert_eq!(rope.visited(1).unwrap().len(), 13);
+        assert_eq!(rope.visited(9).unwrap().len(), 1);
+        assert_eq!(rope.visited(0).unwrap().len(), count_tail_positions(&input, 1).unwrap());
+        assert!(rope.visited(2).is_none());
+        assert_eq!(rope.knots[0], Position(2, 2));
+    }
+
+    #[test]
+    fn test_trace() {
+        let frames = trace(&PuzzleInput::new("R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2"), 2).unwrap();
+        assert_eq!(frames.len(), 9);
+        assert_eq!(frames[0], "== initial ==\n\nH\n");
+        assert_eq!(frames[1], "== R 4 ==\n\ns##1H\n");
+        assert_eq!(frames[2], "== U 4 ==\n\n....H\n....1\n....#\n....#\ns###.\n");
+
+        let frames = trace(&PuzzleInput::new("U 3\nR 1"), 12).unwrap();
+        assert_eq!(frames[1], "== U 3 ==\n\nH\n1\n2\n3\n");
+        assert_eq!(frames[2], "== R 1 ==\n\n.H\n1.\n2.\n3.\n");
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test_bounded(
+            DAY,
+            &["R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2"],
+            10_000,
+            solve_a,
+            solve_b,
+        );
     }
 }
@@ -1,126 +1,241 @@
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 10;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: \n{}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input).map(|crt| format!("\n{}", crt)));
 }
 
-#[derive(Copy, Clone)]
-enum Instruction {
+/// One of the CPU's registers, named by a lowercase letter like the `x` register of the puzzle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Register(u8);
+
+impl Register {
+    const X: Register = Register(b'x' - b'a');
+
+    fn parse(name: &str) -> Result<Self, SolveError> {
+        match name.as_bytes() {
+            [c @ b'a'..=b'z'] => Ok(Register(c - b'a')),
+            _ => Err(SolveError::new(format!("Invalid register: {}", name))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Operation {
     Nop,
-    AddX(i32),
+    Add(Register, i32),
+}
+
+/// Decodes the operands of an instruction. The register is given for instructions whose
+/// mnemonic is followed by a register name, like `addx`.
+type Decode = fn(Option<Register>, &[&str]) -> Result<Operation, SolveError>;
+
+/// An entry of the instruction set: the mnemonic, whether it is suffixed with a register and
+/// how many cycles the instruction takes to complete.
+struct InstructionSpec {
+    mnemonic: &'static str,
+    register_suffix: bool,
+    cycles: usize,
+    decode: Decode,
+}
+
+const INSTRUCTION_SET: [InstructionSpec; 2] = [
+    InstructionSpec {
+        mnemonic: "noop",
+        register_suffix: false,
+        cycles: 1,
+        decode: |_, operands| match operands {
+            [] => Ok(Operation::Nop),
+            _ => Err(SolveError::new("noop takes no operands")),
+        },
+    },
+    InstructionSpec {
+        mnemonic: "add",
+        register_suffix: true,
+        cycles: 2,
+        decode: |register, operands| match (register, operands) {
+            (Some(register), [value]) => Ok(Operation::Add(register, value.parse()?)),
+            _ => Err(SolveError::new("add takes a register and a value, like \"addx 3\"")),
+        },
+    },
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Instruction {
+    operation: Operation,
+    cycles: usize,
 }
 
 impl Instruction {
-    fn new(val: String) -> Self {
-        let mut parts = val.split_whitespace();
-        let instruction = parts.next().unwrap();
-        let value = parts.next().map(|p| p.parse::<i32>().unwrap());
-
-        match instruction {
-            "noop" => Instruction::Nop,
-            "addx" => Instruction::AddX(value.unwrap()),
-            _ => panic!("Unknown instruction: {}", instruction),
+    fn new(val: &str) -> Result<Self, SolveError> {
+        let parts: Vec<_> = val.split_whitespace().collect();
+        let (name, operands) = parts
+            .split_first()
+            .ok_or_else(|| SolveError::new("Expected an instruction"))?;
+
+        for spec in &INSTRUCTION_SET {
+            let register = match (spec.register_suffix, name.strip_prefix(spec.mnemonic)) {
+                (false, Some("")) => None,
+                (true, Some(register)) if !register.is_empty() => Some(Register::parse(register)?),
+                _ => continue,
+            };
+
+            return Ok(Instruction {
+                operation: (spec.decode)(register, operands)?,
+                cycles: spec.cycles,
+            });
         }
+
+        Err(SolveError::new(format!("Unknown instruction: {}", val)))
     }
 }
 
+fn parse_program(input: &PuzzleInput) -> Result<Vec<Instruction>, SolveError> {
+    input.parse_lines(Instruction::new)
+}
+
+/// Called during every cycle with its 1-based number, before the instruction executing in
+/// that cycle takes effect.
+type Observer<'a> = dyn FnMut(usize, &Cpu) + 'a;
+
 struct Cpu {
-    x_register: i32,
+    /// The `x` register starts at 1 like in the puzzle, all other registers at 0.
+    registers: [i32; 26],
+    program: Vec<Instruction>,
+    program_counter: usize,
+    /// Number of completed cycles.
     cycles: usize,
-    instructions: Vec<Instruction>,
-    current_instruction: Option<Instruction>,
+    /// Cycles left until the instruction at the program counter completes.
+    remaining_cycles: usize,
 }
 
 impl Cpu {
-    fn new(input: &PuzzleInput) -> Self {
-        let instructions = input
-            .lines()
-            .map(Instruction::new)
-            .collect();
+    fn new(program: Vec<Instruction>) -> Self {
+        let mut registers = [0; 26];
+        registers[Register::X.0 as usize] = 1;
 
         Self {
-            x_register: 1,
+            registers,
+            program,
+            program_counter: 0,
             cycles: 0,
-            instructions,
-            current_instruction: None,
+            remaining_cycles: 0,
         }
     }
 
-    fn step(&mut self) {
-        if let Some(instruction) = self.current_instruction {
-
-            match instruction {
-                Instruction::AddX(val) => {
-                    self.x_register += val;
-                },
-                _ => panic!("Unknown instruction found"),
-            }
+    fn register(&self, register: Register) -> i32 {
+        self.registers[register.0 as usize]
+    }
 
-            self.current_instruction = None;
-        } else {
-            let instruction = self.instructions.remove(0);
+    /// Runs a single cycle, or does nothing if the program is done. On errors the program
+    /// counter is left at the failing instruction.
+    fn step(&mut self, observe: &mut Observer) -> Result<(), SolveError> {
+        let instruction = match self.program.get(self.program_counter) {
+            Some(instruction) => *instruction,
+            None => return Ok(()),
+        };
+        if self.remaining_cycles == 0 {
+            self.remaining_cycles = instruction.cycles;
+        }
 
-            match instruction {
-                Instruction::Nop => {}
-                Instruction::AddX(_) => {
-                    self.current_instruction = Some(instruction);
+        self.cycles += 1;
+        observe(self.cycles, self);
+        self.remaining_cycles -= 1;
+
+        if self.remaining_cycles == 0 {
+            match instruction.operation {
+                Operation::Nop => {}
+                Operation::Add(register, value) => {
+                    let register = &mut self.registers[register.0 as usize];
+                    *register = register
+                        .checked_add(value)
+                        .ok_or_else(|| SolveError::new("Register overflow"))?;
                 }
             }
+            self.program_counter += 1;
         }
 
-        self.cycles += 1;
+        Ok(())
     }
 
     fn is_done(&self) -> bool {
-        self.instructions.is_empty() && self.current_instruction.is_none()
+        self.program_counter >= self.program.len()
     }
-}
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    let mut cpu = Cpu::new(input);
+    fn run(&mut self, observe: &mut Observer) -> Result<(), SolveError> {
+        while !self.is_done() {
+            self.step(observe)?;
+        }
+        Ok(())
+    }
+}
 
-    let mut signal_strength_sum = 0;
+/// Parses and runs the program, annotating errors while running with the line of the failing
+/// instruction.
+fn run_program(input: &PuzzleInput, observe: &mut Observer) -> Result<(), SolveError> {
+    let mut cpu = Cpu::new(parse_program(input)?);
 
-    while !cpu.is_done() {
-        let previous_x = cpu.x_register;
+    cpu.run(observe).map_err(|e| match input.numbered_lines().nth(cpu.program_counter) {
+        Some((number, line)) => e.at_line(number, line),
+        None => e,
+    })
+}
 
-        cpu.step();
+/// Sums up the signal strength during the 20th cycle and every 40 cycles after that.
+struct SignalSampler {
+    sum: i128,
+}
 
-        if cpu.cycles >= 20 && (cpu.cycles - 20) % 40 == 0 {
-            signal_strength_sum += previous_x * cpu.cycles as i32;
+impl SignalSampler {
+    fn observe(&mut self, cycle: usize, cpu: &Cpu) {
+        if cycle >= 20 && (cycle - 20).is_multiple_of(40) {
+            self.sum += cpu.register(Register::X) as i128 * cycle as i128;
         }
     }
+}
 
-    signal_strength_sum as usize
+/// The CRT draws one pixel per cycle, which is lit if the three pixels wide sprite centered
+/// on the x register covers it.
+struct Crt {
+    pixels: [[char; 40]; 6],
 }
 
-fn solve_b(input: &PuzzleInput) -> String {
-    let mut cpu = Cpu::new(input);
+impl Crt {
+    fn observe(&mut self, cycle: usize, cpu: &Cpu) {
+        let position = cycle - 1;
+        let (crt_x, y) = (position % 40, position / 40);
+        let sprite_x = cpu.register(Register::X);
 
-    let mut pixels = [['.'; 40]; 6];
+        if y < self.pixels.len() && (crt_x as i64 - sprite_x as i64).abs() <= 1 {
+            self.pixels[y][crt_x] = '#';
+        }
+    }
 
-    while !cpu.is_done() {
-        let sprite_x = cpu.x_register % 40;
-        let crt_x = cpu.cycles % 40;
-        let y = cpu.cycles / 40;
+    fn render(&self) -> String {
+        self.pixels
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
 
-        if sprite_x >= 0 && (crt_x as i32 - sprite_x).abs() <= 1 {
-            pixels[y as usize][crt_x] = '#';
-        }
+fn solve_a(input: &PuzzleInput) -> Result<i128, SolveError> {
+    let mut sampler = SignalSampler { sum: 0 };
 
-        cpu.step();
+    run_program(input, &mut |cycle, cpu| sampler.observe(cycle, cpu))?;
+    Ok(sampler.sum)
+}
 
-    }
+fn solve_b(input: &PuzzleInput) -> Result<String, SolveError> {
+    let mut crt = Crt {
+        pixels: [['.'; 40]; 6],
+    };
 
-    
-    pixels
-        .iter()
-        .map(|row| row.iter().collect::<String>())
-        .collect::<Vec<String>>()
-        .join("\n")
+    run_program(input, &mut |cycle, cpu| crt.observe(cycle, cpu))?;
+    Ok(crt.render())
 }
 
 #[cfg(test)]
@@ -277,8 +392,8 @@ noop";
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
@@ -286,45 +401,79 @@ noop";
         let input = PuzzleInput::new("noop
 addx 3
 addx -5");
-        let mut cpu = Cpu::new(&input);
+        let mut cpu = Cpu::new(parse_program(&input).unwrap());
+        let mut observed = vec![];
+        let mut observe =
+            |cycle: usize, cpu: &Cpu| observed.push((cycle, cpu.register(Register::X)));
 
-        cpu.step();
-        assert_eq!(cpu.x_register, 1);
+        cpu.step(&mut observe).unwrap();
+        assert_eq!(cpu.register(Register::X), 1);
         assert_eq!(cpu.cycles, 1);
 
-        cpu.step();
-        assert_eq!(cpu.x_register, 1);
+        cpu.step(&mut observe).unwrap();
+        assert_eq!(cpu.register(Register::X), 1);
         assert_eq!(cpu.cycles, 2);
 
-        cpu.step();
-        assert_eq!(cpu.x_register, 4);
+        cpu.step(&mut observe).unwrap();
+        assert_eq!(cpu.register(Register::X), 4);
         assert_eq!(cpu.cycles, 3);
 
-        cpu.step();
-        assert_eq!(cpu.x_register, 4);
+        cpu.step(&mut observe).unwrap();
+        assert_eq!(cpu.register(Register::X), 4);
         assert_eq!(cpu.cycles, 4);
 
-        cpu.step();
-        assert_eq!(cpu.x_register, -1);
+        cpu.step(&mut observe).unwrap();
+        assert_eq!(cpu.register(Register::X), -1);
         assert_eq!(cpu.cycles, 5);
 
         assert!(cpu.is_done());
+        cpu.step(&mut observe).unwrap();
+        assert_eq!(cpu.cycles, 5);
+        assert_eq!(observed, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+    }
+
+    #[test]
+    fn test_registers_and_errors() {
+        let input = PuzzleInput::new("addy 2\nnoop\naddx -3\naddy 4");
+        let mut cpu = Cpu::new(parse_program(&input).unwrap());
+        cpu.run(&mut |_, _| {}).unwrap();
+        assert_eq!(cpu.cycles, 7);
+        assert_eq!(cpu.register(Register::X), -2);
+        assert_eq!(cpu.register(Register::parse("y").unwrap()), 6);
+
+        let overflow = PuzzleInput::new("noop\naddx 2147483646\naddx 1\nnoop");
+        let error = solve_a(&overflow).unwrap_err();
+        assert_eq!(error.line_number(), Some(3));
+        assert_eq!(error.message(), "Register overflow");
+
+        let error = parse_program(&PuzzleInput::new("noop\njmp 3")).unwrap_err();
+        assert_eq!(error.line_number(), Some(2));
+        assert_eq!(error.message(), "Unknown instruction: jmp 3");
+        assert!(parse_program(&PuzzleInput::new("addX 1")).is_err());
+        assert!(parse_program(&PuzzleInput::new("add 1")).is_err());
+        assert!(parse_program(&PuzzleInput::new("addx")).is_err());
+        assert!(parse_program(&PuzzleInput::new("noop 1")).is_err());
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 13140);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 13140);
     }
 
     #[test]
     fn test_solve_b() {
-        println!("{}", solve_b(&PuzzleInput::new(TEST_INPUT)));
+        println!("{}", solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap());
 
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), "##..##..##..##..##..##..##..##..##..##..
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), "##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
 ####....####....####....####....####....
 #####.....#####.....#####.....#####.....
 ######......######......######......####
 #######.......#######.......#######.....");
     }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
+    }
 }
@@ -1,10 +1,28 @@
-use aoc_utils::PuzzleInput;
+use std::collections::HashSet;
+
+use aoc_utils::{cycle, print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 11;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
+}
+
+/// Parses a single line of a monkey description, annotating errors with that line.
+fn parse_line<T>(
+    (number, line): (usize, &str),
+    parse: impl FnOnce(&str) -> Result<T, SolveError>,
+) -> Result<T, SolveError> {
+    parse(line).map_err(|e| e.at_line(number, line))
+}
+
+/// Returns the part of a line after the given label, e.g. "13" for "  Test: divisible by 13".
+fn strip_label<'a>(line: &'a str, label: &str) -> Result<&'a str, SolveError> {
+    line.trim()
+        .strip_prefix(label)
+        .map(str::trim)
+        .ok_or_else(|| SolveError::new(format!("Expected line starting with \"{}\"", label)))
 }
 
 #[derive(Clone)]
@@ -15,24 +33,29 @@ struct Operation {
 }
 
 impl Operation {
-    fn new(s: &str) -> Self {
-        let mut parts = s.split_whitespace();
-        let _ = parts.next(); // Operation:
-        let _ = parts.next(); // new
-        let _ = parts.next(); // =
-        let _ = parts.next(); // old
+    fn new(s: &str) -> Result<Self, SolveError> {
+        let parts: Vec<_> = s.split_whitespace().collect();
+        let (op, value_str) = match parts[..] {
+            ["Operation:", "new", "=", "old", op @ ("+" | "*"), value_str] => (op, value_str),
+            _ => return Err(SolveError::new("Expected operation like \"new = old * 19\"")),
+        };
 
-        let op = parts.next().unwrap(); // + or *
-        let value_str = parts.next().unwrap(); // number or old
+        let is_old_value_used = value_str == "old";
+        let value = if is_old_value_used {
+            None
+        } else {
+            Some(value_str.parse()?)
+        };
 
-        Self {
+        Ok(Self {
             is_multiply: op == "*",
-            is_old_value_used: value_str == "old",
-            value: value_str.parse().ok(),
-        }
+            is_old_value_used,
+            value,
+        })
     }
 
-    fn apply(&self, old_value: u64) -> u64 {
+    /// Applies the operation in `u128`, which can't overflow for `u64` operands.
+    fn apply(&self, old_value: u64) -> u128 {
         let other_operand = if self.is_old_value_used {
             old_value
         } else {
@@ -40,9 +63,9 @@ impl Operation {
         };
 
         if self.is_multiply {
-            old_value * other_operand
+            old_value as u128 * other_operand as u128
         } else {
-            old_value + other_operand
+            old_value as u128 + other_operand as u128
         }
     }
 }
@@ -58,103 +81,230 @@ struct Monkey {
 }
 
 impl Monkey {
-    fn new(lines: Vec<String>) -> Self {
-        let starting_items = lines[1]
-            .replace("Starting items: ", "")
-            .trim()
-            .split(", ")
-            .map(|s| s.parse().unwrap())
-            .collect();
-
-        let operation = Operation::new(&lines[2]);
-        let test_divisor = lines[3]
-            .replace("Test: divisible by", "")
-            .trim()
-            .parse()
-            .unwrap();
-
-        let true_target_monkey = lines[4]
-            .replace("If true: throw to monkey", "")
-            .trim()
-            .parse()
-            .unwrap();
-        let false_target_monkey = lines[5]
-            .replace("If false: throw to monkey", "")
-            .trim()
-            .parse()
-            .unwrap();
-
-        Monkey {
+    fn new(lines: &[(usize, &str)]) -> Result<Self, SolveError> {
+        if lines.len() != 6 {
+            let (number, line) = lines[0];
+            return Err(SolveError::new("Expected 6 lines describing a monkey").at_line(number, line));
+        }
+
+        let starting_items = parse_line(lines[1], |l| {
+            strip_label(l, "Starting items:")?
+                .split(", ")
+                .filter(|s| !s.is_empty())
+                .map(|s| Ok(s.parse()?))
+                .collect()
+        })?;
+
+        let operation = parse_line(lines[2], Operation::new)?;
+        let test_divisor = parse_line(lines[3], |l| {
+            match strip_label(l, "Test: divisible by")?.parse()? {
+                0 => Err(SolveError::new("Divisor must not be zero")),
+                divisor => Ok(divisor),
+            }
+        })?;
+
+        let true_target_monkey = parse_line(lines[4], |l| {
+            Ok(strip_label(l, "If true: throw to monkey")?.parse()?)
+        })?;
+        let false_target_monkey = parse_line(lines[5], |l| {
+            Ok(strip_label(l, "If false: throw to monkey")?.parse()?)
+        })?;
+
+        Ok(Monkey {
             item_worry_levels: starting_items,
             operation,
             test_divisor,
             true_target_monkey,
             false_target_monkey,
             item_inspect_count: 0,
-        }
+        })
     }
 
-    fn step(&mut self, monkey_list: &mut [Monkey], divide_by_three: bool, common_multiple: u64) {
-        while !self.item_worry_levels.is_empty() {
-            self.item_inspect_count += 1;
+    /// Inspects and throws all items of the monkey at `index` in `monkey_list`, which is a
+    /// copy of `self`. Items thrown to the monkey itself are inspected again right away.
+    fn step(
+        &mut self,
+        index: usize,
+        monkey_list: &mut [Monkey],
+        divide_by_three: bool,
+        common_multiple: u64,
+    ) -> Result<(), SolveError> {
+        for mut worry_level in std::mem::take(&mut self.item_worry_levels) {
+            let mut seen = HashSet::new();
+
+            loop {
+                self.item_inspect_count += 1;
 
-            let worry_level = self.item_worry_levels.remove(0);
-            let mut new_worry_level = self.operation.apply(worry_level);
-            if divide_by_three {
-                new_worry_level /= 3;
+                let mut new_worry_level = self.operation.apply(worry_level);
+                if divide_by_three {
+                    new_worry_level /= 3;
+                }
+                let target = if new_worry_level.is_multiple_of(self.test_divisor as u128) {
+                    self.true_target_monkey
+                } else {
+                    self.false_target_monkey
+                };
+                worry_level = (new_worry_level % common_multiple as u128) as u64;
+
+                if target != index {
+                    monkey_list[target].item_worry_levels.push(worry_level);
+                    break;
+                }
+                if !seen.insert(worry_level) {
+                    return Err(endless_self_throws(index, worry_level));
+                }
             }
+        }
 
-            if new_worry_level % self.test_divisor == 0 {
-                monkey_list[self.true_target_monkey]
-                    .item_worry_levels
-                    .push(new_worry_level % common_multiple);
-            } else {
-                monkey_list[self.false_target_monkey]
-                    .item_worry_levels
-                    .push(new_worry_level % common_multiple);
+        Ok(())
+    }
+}
+
+/// Parses all monkeys and checks that the monkeys they throw to exist.
+fn parse_monkeys(input: &PuzzleInput) -> Result<Vec<Monkey>, SolveError> {
+    let blocks = input.blocks();
+    let monkeys = blocks
+        .iter()
+        .map(|lines| Monkey::new(lines))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (lines, monkey) in blocks.iter().zip(&monkeys) {
+        for (target, (number, line)) in [
+            (monkey.true_target_monkey, lines[4]),
+            (monkey.false_target_monkey, lines[5]),
+        ] {
+            if target >= monkeys.len() {
+                return Err(SolveError::new(format!("Monkey {} doesn't exist", target))
+                    .at_line(number, line));
             }
         }
     }
+
+    Ok(monkeys)
+}
+
+/// The error for an item that a monkey would keep throwing to itself forever.
+fn endless_self_throws(monkey: usize, worry_level: u64) -> SolveError {
+    SolveError::new(format!(
+        "Monkey {} keeps throwing the item with worry level {} to itself",
+        monkey, worry_level
+    ))
+}
+
+/// Returns the product of all test divisors, which keeps the divisibility tests of all monkeys
+/// intact when worry levels are reduced modulo it.
+fn common_multiple(monkeys: &[Monkey]) -> Result<u64, SolveError> {
+    monkeys
+        .iter()
+        .try_fold(1u64, |product, m| product.checked_mul(m.test_divisor))
+        .ok_or_else(|| SolveError::new("The product of the test divisors is too large"))
 }
 
-fn simulate(input:&PuzzleInput, rounds: u64, part_a: bool) -> u64 {
-    let mut monkeys: Vec<_> = input
-        .raw_input
-        .split("\n\n")
-        .map(|lines| Monkey::new(lines.split('\n').map(|s| s.to_string()).collect()))
-        .collect();
+/// Multiplies the two largest inspection counts.
+fn monkey_business(mut inspect_counts: Vec<u64>) -> Result<u64, SolveError> {
+    if inspect_counts.is_empty() {
+        return Err(SolveError::new("Input contains no monkeys"));
+    }
 
-    let common_multiple = monkeys.iter().map(|m| m.test_divisor).product();
+    inspect_counts.sort();
+    inspect_counts.reverse();
+
+    inspect_counts
+        .iter()
+        .take(2)
+        .try_fold(1u64, |product, count| product.checked_mul(*count))
+        .ok_or_else(|| SolveError::new("Monkey business is too large"))
+}
+
+fn simulate(input:&PuzzleInput, rounds: u64, part_a: bool) -> Result<u64, SolveError> {
+    let mut monkeys = parse_monkeys(input)?;
+    let common_multiple = common_multiple(&monkeys)?;
 
     for _ in 0..rounds {
         for i in 0..monkeys.len() {
             let mut monkey = monkeys[i].clone();
-            monkey.step(&mut monkeys, part_a,  common_multiple);
+            monkey.step(i, &mut monkeys, part_a, common_multiple)?;
             monkeys[i] = monkey;
         }
     }
 
-    let mut scores = monkeys
-        .iter()
-        .map(|m| m.item_inspect_count)
-        .collect::<Vec<_>>();
-    scores.sort();
-    scores.reverse();
-
-    scores.iter().take(2).product()
+    monkey_business(monkeys.iter().map(|m| m.item_inspect_count).collect())
 }
 
-fn solve_a(input: &PuzzleInput) -> u64 {
+fn solve_a(input: &PuzzleInput) -> Result<u64, SolveError> {
     simulate(input, 20, true)
 }
 
-fn solve_b(input: &PuzzleInput) -> u64 {
-    simulate(input, 10_000, false)
+/// Follows a single item through one round, counting the inspections of each monkey.
+/// The item stays in the round as long as it's thrown to monkeys that haven't had their turn yet,
+/// including the current monkey itself.
+fn item_round(
+    monkeys: &[Monkey],
+    common_multiple: u64,
+    (mut monkey, mut worry_level, mut inspect_counts): (usize, u64, Vec<u64>),
+) -> Result<(usize, u64, Vec<u64>), SolveError> {
+    let mut seen = HashSet::new();
+
+    loop {
+        let current = &monkeys[monkey];
+        inspect_counts[monkey] += 1;
+        worry_level = (current.operation.apply(worry_level) % common_multiple as u128) as u64;
+
+        let target = if worry_level.is_multiple_of(current.test_divisor) {
+            current.true_target_monkey
+        } else {
+            current.false_target_monkey
+        };
+
+        if target < monkey {
+            return Ok((target, worry_level, inspect_counts));
+        }
+        if target == monkey && !seen.insert(worry_level) {
+            return Err(endless_self_throws(monkey, worry_level));
+        }
+        if target > monkey {
+            seen.clear();
+        }
+        monkey = target;
+    }
+}
+
+fn solve_b(input: &PuzzleInput) -> Result<u64, SolveError> {
+    const ROUNDS: usize = 10_000;
+
+    let monkeys = parse_monkeys(input)?;
+    let common_multiple = common_multiple(&monkeys)?;
+
+    // Items don't influence each other without the relief of part A, so every item can be
+    // followed on its own until it's back at the same monkey with the same worry level.
+    let mut inspect_counts = vec![0; monkeys.len()];
+    for (i, monkey) in monkeys.iter().enumerate() {
+        for &worry_level in &monkey.item_worry_levels {
+            let initial = (i, worry_level % common_multiple, vec![0; monkeys.len()]);
+            let (states, cycle) = cycle::try_find_cycle(
+                initial,
+                ROUNDS,
+                |(monkey, worry_level, _)| (*monkey, *worry_level),
+                |state| item_round(&monkeys, common_multiple, state.clone()),
+            )?;
+
+            for (m, count) in inspect_counts.iter_mut().enumerate() {
+                *count += match cycle {
+                    Some(cycle) => cycle.extrapolate(ROUNDS, |round| states[round].2[m]),
+                    None => states[ROUNDS].2[m],
+                };
+            }
+        }
+    }
+
+    monkey_business(inspect_counts)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_utils::check::assert_agree;
+    use aoc_utils::generate;
 
     const TEST_INPUT: &str = "Monkey 0:
   Starting items: 79, 98
@@ -187,17 +337,74 @@ Monkey 3:
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 10605);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 10605);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 2713310158);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 2713310158);
+    }
+
+    #[test]
+    fn test_solve_b_agrees_with_simulation() {
+        assert_agree(
+            |rng| generate::monkeys(rng, 20),
+            |input| simulate(input, 10_000, false).unwrap(),
+            |input| solve_b(input).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let large_divisors = TEST_INPUT
+            .replace("divisible by 23", "divisible by 4294967291")
+            .replace("divisible by 19", "divisible by 4294967279");
+        let error = solve_b(&PuzzleInput::new(large_divisors)).unwrap_err();
+        assert_eq!(error.message(), "The product of the test divisors is too large");
+
+        let large_items = TEST_INPUT.replace("79, 98", "18446744073709551615");
+        assert!(solve_a(&PuzzleInput::new(large_items.as_str())).is_ok());
+        assert!(solve_b(&PuzzleInput::new(large_items.as_str())).is_ok());
+    }
+
+    #[test]
+    fn test_no_monkeys() {
+        let input = PuzzleInput::new("");
+        assert_eq!(solve_a(&input).unwrap_err().message(), "Input contains no monkeys");
+        assert_eq!(solve_b(&input).unwrap_err().message(), "Input contains no monkeys");
+    }
+
+    #[test]
+    fn test_self_throws() {
+        // monkey 1 throws odd worry levels to itself, which stay odd after adding 2
+        let input = TEST_INPUT
+            .replace("old + 6", "old + 2")
+            .replace("by 19", "by 2")
+            .replace("If false: throw to monkey 0", "If false: throw to monkey 1");
+        let input = PuzzleInput::new(input);
+        for error in [solve_a(&input).unwrap_err(), solve_b(&input).unwrap_err()] {
+            assert!(error.message().starts_with("Monkey 1 keeps throwing the item"));
+        }
+
+        // monkey 3 throws to itself only once, since adding 3 makes the item divisible by 17
+        let input = TEST_INPUT
+            .replace("If false: throw to monkey 1\n\nMonkey 2", "X")
+            .replace("If false: throw to monkey 1", "If false: throw to monkey 3")
+            .replace("X", "If false: throw to monkey 1\n\nMonkey 2");
+        assert_eq!(
+            simulate(&PuzzleInput::new(input.as_str()), 10_000, false).unwrap(),
+            solve_b(&PuzzleInput::new(input.as_str())).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
@@ -1,4 +1,4 @@
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 use pathfinding::prelude::dijkstra;
 const DAY: u8 = 12;
 const START_VALUE: u32 = 0;
@@ -6,30 +6,28 @@ const END_VALUE: u32 = 27;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
 }
 
-fn parse_input(input: &PuzzleInput) -> Vec<Vec<u32>> {
-    input
-        .lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| match c {
-                    'S' => START_VALUE,
-                    'E' => END_VALUE,
-                    c => c as u32 - 'a' as u32 + 1,
-                })
-                .collect()
-        })
-        .collect()
+fn parse_input(input: &PuzzleInput) -> Result<Vec<Vec<u32>>, SolveError> {
+    input.parse_lines(|line| {
+        line.chars()
+            .map(|c| match c {
+                'S' => Ok(START_VALUE),
+                'E' => Ok(END_VALUE),
+                'a'..='z' => Ok(c as u32 - 'a' as u32 + 1),
+                _ => Err(SolveError::new(format!("Invalid elevation: {}", c))),
+            })
+            .collect()
+    })
 }
 
-fn find_shortest_path(map: &[Vec<u32>], start_points: &[(usize, usize)]) -> u32  {
+fn find_shortest_path(map: &[Vec<u32>], start_points: &[(usize, usize)]) -> Result<u32, SolveError>  {
     let is_at_end = |(x, y): &(usize, usize)| map[*y][*x] == END_VALUE;
 
     start_points.iter()
-    .map(|start| {
+    .filter_map(|start| {
         let result = dijkstra(
             start,
             |&(x, y)| {
@@ -42,17 +40,17 @@ fn find_shortest_path(map: &[Vec<u32>], start_points: &[(usize, usize)]) -> u32
                 }
     
                 // Right
-                if x < map[y].len() - 1 && map[y][x+1] <= current_weight + 1 {
+                if x + 1 < map[y].len() && map[y][x+1] <= current_weight + 1 {
                     neighbors.push(((x+1, y), 1));
                 }
     
                 // Up
-                if y > 0 && map[y-1][x] <= current_weight + 1 {
+                if y > 0 && x < map[y-1].len() && map[y-1][x] <= current_weight + 1 {
                     neighbors.push(((x, y-1), 1));
                 }
     
                 // Down
-                if y < map.len() - 1 && map[y+1][x] <= current_weight + 1 {
+                if y + 1 < map.len() && x < map[y+1].len() && map[y+1][x] <= current_weight + 1 {
                     neighbors.push(((x, y+1), 1));
                 }
     
@@ -61,27 +59,27 @@ fn find_shortest_path(map: &[Vec<u32>], start_points: &[(usize, usize)]) -> u32
             is_at_end,
         );
     
-        result.map(|(_, cost)| cost).unwrap_or(u32::MAX)
+        result.map(|(_, cost)| cost)
     })
     .min()
-    .unwrap()
+    .ok_or_else(|| SolveError::new("No path to the best signal location found"))
 }
 
-fn solve_a(input: &PuzzleInput) -> u32 {
-    let map = parse_input(input);
+fn solve_a(input: &PuzzleInput) -> Result<u32, SolveError> {
+    let map = parse_input(input)?;
 
     let start = map
         .iter()
         .enumerate()
         .find(|(_, row)| row.contains(&START_VALUE))
         .map(|(y, row)| (row.iter().position(|&c| c == START_VALUE).unwrap(), y))
-        .unwrap();
+        .ok_or_else(|| SolveError::new("No start position found"))?;
 
     find_shortest_path(&map, &[start])
 }
 
-fn solve_b(input: &PuzzleInput) -> u32 {
-    let map = parse_input(input);
+fn solve_b(input: &PuzzleInput) -> Result<u32, SolveError> {
+    let map = parse_input(input)?;
 
     let starting_points = map
         .iter()
@@ -110,17 +108,22 @@ abdefghi";
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
+        solve_a(&input).unwrap();
         //solve_b(&input); //takes too long (about 11 seconds)
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 31);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 31);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 29);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 29);
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
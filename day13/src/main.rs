@@ -1,12 +1,12 @@
 use std::cmp::Ordering;
 
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 13;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
 }
 
 #[derive(Clone, Debug)]
@@ -15,52 +15,58 @@ enum Value {
     List(Vec<Value>),
 }
 
+/// Deepest nesting of lists that is accepted, so that parsing and comparing packets can't
+/// overflow the stack.
+const MAX_DEPTH: usize = 100;
+
 impl Value {
-    fn parse(line: &str) -> Value {
-        println!("{}", line);
+    fn parse(line: &str) -> Result<Value, SolveError> {
+        Value::parse_nested(line, 0)
+    }
+
+    fn parse_nested(line: &str, nesting: usize) -> Result<Value, SolveError> {
         if line.starts_with('[') {
-            let without_brackets = &line[1..line.len() - 1];
+            if nesting == MAX_DEPTH {
+                return Err(SolveError::new(format!(
+                    "Lists are nested deeper than {} levels",
+                    MAX_DEPTH
+                )));
+            }
+
+            let without_brackets = line
+                .strip_prefix('[')
+                .and_then(|l| l.strip_suffix(']'))
+                .ok_or_else(|| SolveError::new(format!("Unclosed list: {}", line)))?;
             let mut values = vec![];
 
             let mut depth = 0;
             let mut start = 0;
-            for (i, c) in without_brackets.chars().enumerate() {
+            for (i, c) in without_brackets.char_indices() {
                 match c {
                     '[' => depth += 1,
+                    ']' if depth == 0 => {
+                        return Err(SolveError::new(format!("Unbalanced brackets: {}", line)))
+                    }
                     ']' => depth -= 1,
                     ',' if depth == 0 => {
-                        values.push(Value::parse(&without_brackets[start..i]));
+                        values.push(Value::parse_nested(&without_brackets[start..i], nesting + 1)?);
                         start = i + 1;
                     }
                     _ => {}
                 }
             }
 
+            if depth != 0 {
+                return Err(SolveError::new(format!("Unbalanced brackets: {}", line)));
+            }
+
             if !without_brackets.is_empty() {
-                values.push(Value::parse(&without_brackets[start..]));
+                values.push(Value::parse_nested(&without_brackets[start..], nesting + 1)?);
             }
 
-            Value::List(values)
+            Ok(Value::List(values))
         } else {
-            Value::Number(line.parse().unwrap())
-        }
-    }
-
-    fn format(&self) -> String {
-        match self {
-            Value::Number(n) => n.to_string(),
-            Value::List(l) => {
-                let mut s = String::new();
-                s.push('[');
-                for (i, v) in l.iter().enumerate() {
-                    if i > 0 {
-                        s.push(',');
-                    }
-                    s.push_str(&v.format());
-                }
-                s.push(']');
-                s
-            }
+            Ok(Value::Number(line.parse()?))
         }
     }
 }
@@ -76,17 +82,23 @@ enum Order {
 struct Pair(Value, Value);
 
 impl Pair {
-    fn parse(lines: &str) -> Pair {
-        let mut lines = lines.lines();
-        let l = Value::parse(lines.next().unwrap());
-        let r = Value::parse(lines.next().unwrap());
-        Pair(l, r)
+    fn parse(lines: &[(usize, &str)]) -> Result<Pair, SolveError> {
+        if lines.len() != 2 {
+            let (number, line) = lines[0];
+            return Err(SolveError::new("Expected a pair of two packets").at_line(number, line));
+        }
+
+        let values = lines
+            .iter()
+            .map(|(number, line)| Value::parse(line).map_err(|e| e.at_line(*number, line)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let [l, r]: [Value; 2] = values.try_into().unwrap();
+        Ok(Pair(l, r))
     }
 
     fn compare(&self) -> Order {
         match self {
             Pair(Value::Number(l), Value::Number(r)) => {
-                println!("{} {}", l, r);
                 match l.cmp(r) {
                     Ordering::Less => Order::Right,
                     Ordering::Equal => Order::Neutral,
@@ -106,7 +118,10 @@ impl Pair {
                     i += 1;
                 }
 
-                if i == l.len() {
+                if l.len() == r.len() {
+                    // Both lists are equal, continue with the next values
+                    Order::Neutral
+                } else if i == l.len() {
                     // Left run out of values
                     Order::Right
                 } else {
@@ -123,63 +138,89 @@ impl Pair {
     }
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    let pairs: Vec<_> = input.raw_input.split("\n\n").map(Pair::parse).collect();
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    let pairs = input
+        .blocks()
+        .iter()
+        .map(|lines| Pair::parse(lines))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    pairs
+    Ok(pairs
         .iter()
         .enumerate()
-        .filter(|(_, p)| {
-            println!("{:?}", p);
-            println!("{}", p.0.format());
-            println!("{}", p.1.format());
-            println!("{:?}", p.compare());
-            p.compare() == Order::Right
-        })
+        .filter(|(_, p)| p.compare() == Order::Right)
         .map(|(i, _)| i + 1)
-        .sum()
+        .sum())
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    let mut values: Vec<_> = input.lines()
-    .filter(|l| !l.is_empty())
-    .map(|l| Value::parse(&l)).collect();
-
-    let divider_packets = vec!["[[2]]", "[[6]]"];
-    for div in divider_packets.iter() {
-        values.push(Value::parse(div));
-    }
-
-    loop {
-        let mut sorted = true;
-
-        for i in 0..values.len() - 1 {
-            let p = Pair(values[i].clone(), values[i + 1].clone());
+const DIVIDER_PACKETS: [&str; 2] = ["[[2]]", "[[6]]"];
 
-            if let Order::Wrong = p.compare() {
-                sorted = false;
-                // swap values
-                values[i] = p.1;
-                values[i + 1] = p.0;
-            }
-        }
+/// Parses all packets of the input and adds the divider packets at the end. Every packet is
+/// flagged whether it's one of the added dividers, so input packets equal to them don't count.
+fn parse_packets(input: &PuzzleInput) -> Result<Vec<(Value, bool)>, SolveError> {
+    let mut values = input
+        .numbered_lines()
+        .filter(|(_, l)| !l.is_empty())
+        .map(|(number, l)| Ok((Value::parse(l).map_err(|e| e.at_line(number, l))?, false)))
+        .collect::<Result<Vec<_>, SolveError>>()?;
 
-        if sorted {
-            break;
-        }
+    for div in DIVIDER_PACKETS.iter() {
+        values.push((Value::parse(div)?, true));
     }
 
-    values
+    Ok(values)
+}
+
+fn get_decoder_key(sorted_values: &[(Value, bool)]) -> usize {
+    sorted_values
         .iter()
         .enumerate()
-        .filter(|(_, v)| divider_packets.contains(&&v.format()[..]))
+        .filter(|(_, (_, is_divider))| *is_divider)
         .map(|(i, _)| i + 1)
         .product()
 }
 
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    let mut values = parse_packets(input)?;
+
+    values.sort_by(|(l, _), (r, _)| match Pair(l.clone(), r.clone()).compare() {
+        Order::Right => Ordering::Less,
+        Order::Neutral => Ordering::Equal,
+        Order::Wrong => Ordering::Greater,
+    });
+
+    Ok(get_decoder_key(&values))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aoc_utils::check::assert_agree;
+    use aoc_utils::generate;
+
+    /// The original bubble sort based solution, used as reference for the sorting in solve_b.
+    fn solve_b_bubble_sort(input: &PuzzleInput) -> Result<usize, SolveError> {
+        let mut values = parse_packets(input)?;
+
+        loop {
+            let mut sorted = true;
+
+            for i in 0..values.len() - 1 {
+                let p = Pair(values[i].0.clone(), values[i + 1].0.clone());
+
+                if let Order::Wrong = p.compare() {
+                    sorted = false;
+                    values.swap(i, i + 1);
+                }
+            }
+
+            if sorted {
+                break;
+            }
+        }
+
+        Ok(get_decoder_key(&values))
+    }
 
     const TEST_INPUT: &str = "[1,1,3,1,1]
 [1,1,5,1,1]
@@ -208,17 +249,53 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 13);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 13);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 140);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 140);
+    }
+
+    #[test]
+    fn test_invalid_packet() {
+        let error = solve_a(&PuzzleInput::new("[1,1]\n[1,2]\n\n[[1],4\n[2]")).unwrap_err();
+        assert_eq!(error.line_number(), Some(4));
+        assert_eq!(error.line(), Some("[[1],4"));
+    }
+
+    #[test]
+    fn test_packets_equal_to_dividers() {
+        let input = PuzzleInput::new("[[2]]\n[[6]]\n\n[1]\n[[2]]");
+        // sorted: [1], [[2]], [[2]], [[2]] (divider), [[6]], [[6]] (divider)
+        assert_eq!(solve_b(&input).unwrap(), 4 * 6);
+        assert_eq!(solve_b_bubble_sort(&input).unwrap(), 4 * 6);
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let deep = format!("{}{}", "[".repeat(5000), "]".repeat(5000));
+        let error = solve_a(&PuzzleInput::new(format!("[1]\n{}", deep))).unwrap_err();
+        assert_eq!(error.line_number(), Some(2));
+        assert_eq!(error.message(), "Lists are nested deeper than 100 levels");
+
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(Value::parse(&nested).is_ok());
+    }
+
+    #[test]
+    fn test_solve_b_agrees_with_bubble_sort() {
+        assert_agree(|rng| generate::packet_pairs(rng, 20), solve_b_bubble_sort, solve_b);
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
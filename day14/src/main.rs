@@ -1,10 +1,10 @@
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 const DAY: u8 = 14;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
 }
 
 struct Point(usize, usize);
@@ -14,16 +14,18 @@ struct RockLines {
 }
 
 impl RockLines {
-    fn parse(l: String) -> RockLines {
-        RockLines {
+    fn parse(l: &str) -> Result<RockLines, SolveError> {
+        Ok(RockLines {
             points: l
                 .split(" -> ")
                 .map(|s| {
-                    let mut it = s.split(',').map(|s| s.parse::<usize>().unwrap());
-                    Point(it.next().unwrap(), it.next().unwrap())
+                    let (x, y) = s
+                        .split_once(',')
+                        .ok_or_else(|| SolveError::new(format!("Invalid point: {}", s)))?;
+                    Ok(Point(x.parse()?, y.parse()?))
                 })
-                .collect(),
-        }
+                .collect::<Result<_, SolveError>>()?,
+        })
     }
 }
 #[derive(Clone, PartialEq)]
@@ -33,13 +35,38 @@ enum Block {
     Sand,
 }
 
-fn parse_input(input: &PuzzleInput) -> Vec<Vec<Block>> {
-    let rocks: Vec<_> = input
-        .lines()
-        .map(RockLines::parse)
-        .collect();
+/// Width and height of the simulated cave.
+const GRID_SIZE: usize = 1000;
+
+fn parse_rock_lines(input: &PuzzleInput) -> Result<Vec<RockLines>, SolveError> {
+    input.parse_lines(|l| {
+        let rock_line = RockLines::parse(l)?;
+
+        // Leave room for the floor of part B below the lowest rock
+        if let Some(Point(x, y)) = rock_line
+            .points
+            .iter()
+            .find(|Point(x, y)| *x >= GRID_SIZE || *y >= GRID_SIZE - 2)
+        {
+            return Err(SolveError::new(format!("Point {},{} is outside of the cave", x, y)));
+        }
+
+        if rock_line
+            .points
+            .windows(2)
+            .any(|p| p[0].0 != p[1].0 && p[0].1 != p[1].1)
+        {
+            return Err(SolveError::new("Rock lines must be horizontal or vertical"));
+        }
+
+        Ok(rock_line)
+    })
+}
+
+fn parse_input(input: &PuzzleInput) -> Result<Vec<Vec<Block>>, SolveError> {
+    let rocks = parse_rock_lines(input)?;
 
-    let mut grid = vec![vec![Block::Air; 1000]; 1000];
+    let mut grid = vec![vec![Block::Air; GRID_SIZE]; GRID_SIZE];
 
     for rock_line in rocks.iter() {
         let mut start_point = &rock_line.points[0];
@@ -55,15 +82,15 @@ fn parse_input(input: &PuzzleInput) -> Vec<Vec<Block>> {
                     row[start_x] = Block::Rock;
                 }
             } else {
-                for x in min_x..=max_x {
-                    grid[start_y][x] = Block::Rock;
+                for block in &mut grid[start_y][min_x..=max_x] {
+                    *block = Block::Rock;
                 }
             }
             start_point = end_point;
         }
     }
 
-    grid
+    Ok(grid)
 }
 
 fn place_sand(grid: &mut [Vec<Block>]) -> bool {
@@ -74,6 +101,11 @@ fn place_sand(grid: &mut [Vec<Block>]) -> bool {
 
     for _ in 0..grid.len() - 1 {
         let Point(x, y) = sand_pos;
+        if x == 0 || x + 1 >= grid[y].len() {
+            // Sand leaving the cave to the side falls into the abyss
+            return false;
+        }
+
         if grid[y + 1][x] == Block::Air {
             sand_pos = Point(x, y + 1);
             continue;
@@ -97,27 +129,27 @@ fn place_sand(grid: &mut [Vec<Block>]) -> bool {
     false
 }
 
-fn solve_a(input: &PuzzleInput) -> usize {
-    let mut grid = parse_input(input);
+fn solve_a(input: &PuzzleInput) -> Result<usize, SolveError> {
+    let mut grid = parse_input(input)?;
 
     let mut sand_count = 0;
     while place_sand(&mut grid) {
         sand_count += 1;
     }
 
-    sand_count
+    Ok(sand_count)
 }
 
-fn solve_b(input: &PuzzleInput) -> usize {
-    let mut grid = parse_input(input);
+fn solve_b(input: &PuzzleInput) -> Result<usize, SolveError> {
+    let mut grid = parse_input(input)?;
 
     let highest_sand_y = grid
         .iter()
         .enumerate()
-        .filter(|(_, row)| row.iter().any(|b| *b == Block::Rock))
+        .filter(|(_, row)| row.contains(&Block::Rock))
         .map(|(y, _)| y)
         .max()
-        .unwrap();
+        .ok_or_else(|| SolveError::new("Input contains no rock lines"))?;
 
     for x in 0..grid[highest_sand_y].len() {
         grid[highest_sand_y + 2][x] = Block::Rock;
@@ -128,7 +160,7 @@ fn solve_b(input: &PuzzleInput) -> usize {
         sand_count += 1;
     }
 
-    sand_count
+    Ok(sand_count)
 }
 
 #[cfg(test)]
@@ -140,17 +172,22 @@ mod tests {
     #[test]
     fn test_no_panic() {
         let input = PuzzleInput::get_input(DAY);
-        solve_a(&input);
-        solve_b(&input);
+        solve_a(&input).unwrap();
+        solve_b(&input).unwrap();
     }
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 24);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 24);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 93);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 93);
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
@@ -1,95 +1,104 @@
-use aoc_utils::PuzzleInput;
+use std::collections::HashSet;
+
+use aoc_utils::interval::{self, Interval};
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 use regex::Regex;
 const DAY: u8 = 15;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input, 2000000));
-    println!("B: {}", solve_b(&input, 4000000));
+    print_answer("A", solve_a(&input, 2000000));
+    print_answer("B", solve_b(&input, 4000000));
 }
 
-struct Coordinates(i32, i32);
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Coordinates(i64, i64);
 
 impl Coordinates {
-    fn distance(&self, other: &Coordinates) -> i32 {
+    fn distance(&self, other: &Coordinates) -> i64 {
         (self.0 - other.0).abs() + (self.1 - other.1).abs()
     }
 }
 
 struct Sensor {
     position: Coordinates,
-    distance_to_beacon: i32,
+    closest_beacon: Coordinates,
+    distance_to_beacon: i64,
 }
 
 impl Sensor {
-    fn parse(l: String) -> Sensor {
+    fn parse(l: &str) -> Result<Sensor, SolveError> {
         let regex = Regex::new(
-            r"Sensor at x=(-?\d+), y=(-?\d+): closest beacon is at x=(-?\d+), y=(-?\d+)",
+            r"^Sensor at x=(-?\d+), y=(-?\d+): closest beacon is at x=(-?\d+), y=(-?\d+)$",
         )
         .unwrap();
-        let capture = regex.captures(&l).unwrap();
+        let capture = regex.captures(l).ok_or_else(|| {
+            SolveError::new("Expected sensor like \"Sensor at x=2, y=18: closest beacon is at x=-2, y=15\"")
+        })?;
         let capture_groups = capture.iter().skip(1);
         let numbers = capture_groups
-            .map(|x| x.unwrap().as_str().parse::<i32>().unwrap())
-            .collect::<Vec<i32>>();
+            .map(|x| x.unwrap().as_str().parse::<i32>().map(i64::from))
+            .collect::<Result<Vec<i64>, _>>()?;
 
         let position = Coordinates(numbers[0], numbers[1]);
         let closest_beacon = Coordinates(numbers[2], numbers[3]);
 
-        Sensor {
+        Ok(Sensor {
             distance_to_beacon: position.distance(&closest_beacon),
             position,
-        }
+            closest_beacon,
+        })
     }
-}
-
-
-fn is_beacon_possible(x: i32, y: i32, sensors: &[Sensor]) -> (bool, Option<&Sensor>) {
-    for sensor in sensors.iter() {
-        let distance_beacon_to_sensor =sensor.distance_to_beacon;
-        let distance_here_to_sensor = sensor.position.distance(&Coordinates(x, y));
 
-        if distance_here_to_sensor <= distance_beacon_to_sensor {
-            return (false, Some(sensor));
-        }
+    /// Returns the positions of the given row that are at most as far away from the sensor as
+    /// its closest beacon, so no other beacon can be there.
+    fn covered_in_row(&self, y: i64) -> Option<Interval> {
+        let remaining = self.distance_to_beacon - (y - self.position.1).abs();
+        Interval::new(self.position.0 - remaining, self.position.0 + remaining)
     }
+}
 
-    (true, None)
+/// Returns the positions of the given row that are covered by any sensor.
+fn row_coverage(sensors: &[Sensor], y: i64) -> Vec<Interval> {
+    interval::merge(sensors.iter().filter_map(|s| s.covered_in_row(y)))
 }
 
-fn solve_a(input: &PuzzleInput, y_value: i32) -> usize {
-    let sensors: Vec<_> = input.lines().map(Sensor::parse).collect();
+fn solve_a(input: &PuzzleInput, y_value: i64) -> Result<u64, SolveError> {
+    let sensors = input.parse_lines(Sensor::parse)?;
 
-    let result: usize = (-y_value * 3..=y_value*3)
-        .filter(|x| !is_beacon_possible(*x, y_value, &sensors).0)
-        .count();
+    let beacons_in_row = sensors
+        .iter()
+        .map(|s| s.closest_beacon)
+        .filter(|beacon| beacon.1 == y_value)
+        .collect::<HashSet<_>>()
+        .len();
 
-    // Don't ask me why this is consistently one off, lol
-    result - 1
+    let covered = interval::covered_length(sensors.iter().filter_map(|s| s.covered_in_row(y_value)));
+    Ok(covered - beacons_in_row as u64)
 }
 
-fn solve_b(input: &PuzzleInput, x_y_max: usize) -> usize {
-    let sensors: Vec<_> = input.lines().map(Sensor::parse).collect();
+fn solve_b(input: &PuzzleInput, x_y_max: i64) -> Result<i64, SolveError> {
+    let sensors = input.parse_lines(Sensor::parse)?;
+    let search_area = Interval::new(0, x_y_max).unwrap();
 
     for y in 0..=x_y_max {
-        let mut x = 0;
-        loop {
-            let (possible, sensor) = is_beacon_possible(x as i32, y as i32, &sensors);
-            if possible {
-                return x * 4000000 + y;
-            }
-
-            let sensor = sensor.unwrap();
-            x = (sensor.position.0 + sensor.distance_to_beacon - (y as i32 -sensor.position.1).abs()) as usize;
-            x += 1;
-
-            if x >= x_y_max {
-                break;
-            }
-        }
+        let covered: Vec<_> = row_coverage(&sensors, y)
+            .iter()
+            .filter_map(|i| i.intersection(&search_area))
+            .collect();
+
+        // The merged intervals are sorted, so the first gap is either before the first one
+        // or right after it
+        let x = match covered.first() {
+            None => 0,
+            Some(first) if first.start > 0 => 0,
+            Some(first) if first.end < x_y_max => first.end + 1,
+            _ => continue,
+        };
+        return Ok(x * 4000000 + y);
     }
 
-    panic!("No solution found");
+    Err(SolveError::new("No position for the distress beacon found"))
 }
 
 #[cfg(test)]
@@ -113,11 +122,34 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3";
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT), 10), 26);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT), 10).unwrap(), 26);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT), 20), 56000011);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT), 20).unwrap(), 56000011);
+    }
+
+    #[test]
+    fn test_invalid_sensor() {
+        let input = TEST_INPUT.replace("x=8, y=7", "x=8, y=seven");
+        let error = solve_a(&PuzzleInput::new(input), 10).unwrap_err();
+        assert_eq!(error.line_number(), Some(7));
+    }
+
+    #[test]
+    fn test_no_solution() {
+        let input = PuzzleInput::new("Sensor at x=5, y=5: closest beacon is at x=5, y=15");
+        assert!(solve_b(&input, 10).is_err());
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(
+            DAY,
+            &[TEST_INPUT],
+            |input| solve_a(input, 10),
+            |input| solve_b(input, 20),
+        );
     }
 }
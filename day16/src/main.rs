@@ -1,13 +1,13 @@
 use std::collections::HashMap;
 
-use aoc_utils::PuzzleInput;
+use aoc_utils::{print_answer, PuzzleInput, SolveError};
 use regex::Regex;
 const DAY: u8 = 16;
 
 fn main() {
     let input = PuzzleInput::get_input(DAY);
-    println!("A: {}", solve_a(&input));
-    println!("B: {}", solve_b(&input));
+    print_answer("A", solve_a(&input));
+    print_answer("B", solve_b(&input));
 }
 
 struct Valve {
@@ -17,13 +17,17 @@ struct Valve {
 }
 
 impl Valve {
-    fn parse(l: String) -> Self {
+    fn parse(l: &str) -> Result<Self, SolveError> {
         let regex =
             Regex::new("Valve (.+) has flow rate=(.+); tunnels? leads? to valves? (.+)").unwrap();
-        let captures = regex.captures(&l).unwrap();
+        let captures = regex.captures(l).ok_or_else(|| {
+            SolveError::new("Expected valve like \"Valve BB has flow rate=13; tunnels lead to valves CC, AA\"")
+        })?;
 
         let name = captures.get(1).unwrap().as_str().to_owned();
-        let flow_rate = captures.get(2).unwrap().as_str().parse().unwrap();
+        // flow rates of up to 64 valves fit into u32, so the u64 totals over 30 minutes can't
+        // overflow
+        let flow_rate = captures.get(2).unwrap().as_str().parse::<u32>()? as u64;
         let tunnels_to_valves = captures
             .get(3)
             .unwrap()
@@ -32,12 +36,26 @@ impl Valve {
             .map(|s| s.to_owned())
             .collect();
 
-        Self {
+        Ok(Self {
             name,
             tunnels_to_valves,
             flow_rate,
-        }
+        })
+    }
+}
+
+fn parse_input(input: &PuzzleInput) -> Result<Vec<Valve>, SolveError> {
+    let valves = input.parse_lines(Valve::parse)?;
+
+    // the set of open valves is stored as a bitmask
+    if valves.len() > 64 {
+        return Err(SolveError::new("At most 64 valves are supported"));
     }
+    if !valves.iter().any(|v| v.name == "AA") {
+        return Err(SolveError::new("Input contains no starting valve AA"));
+    }
+
+    Ok(valves)
 }
 
 fn get_flow(input: &[Valve], open: u64) -> u64 {
@@ -74,8 +92,8 @@ fn update<'a>(
     }
 }
 
-fn solve_a(input: &PuzzleInput) -> u64 {
-    let input: Vec<_> = input.lines().map(Valve::parse).collect();
+fn solve_a(input: &PuzzleInput) -> Result<u64, SolveError> {
+    let input = parse_input(input)?;
 
     let idx: HashMap<_, _> = input
         .iter()
@@ -123,11 +141,11 @@ fn solve_a(input: &PuzzleInput) -> u64 {
         flow_rate.retain(|(t, _, _), _| *t == time);
     }
 
-    *flow_rate.values().max().unwrap()
+    Ok(flow_rate.values().max().copied().unwrap_or(0))
 }
 
-fn solve_b(input: &PuzzleInput) -> u64 {
-    let input: Vec<_> = input.lines().map(Valve::parse).collect();
+fn solve_b(input: &PuzzleInput) -> Result<u64, SolveError> {
+    let input = parse_input(input)?;
 
     let idx: HashMap<_, _> = input
         .iter()
@@ -190,7 +208,7 @@ fn solve_b(input: &PuzzleInput) -> u64 {
         }
     }
 
-    part2
+    Ok(part2)
 }
 
 #[cfg(test)]
@@ -210,11 +228,33 @@ Valve JJ has flow rate=21; tunnel leads to valve II";
 
     #[test]
     fn test_solve_a() {
-        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)), 1651);
+        assert_eq!(solve_a(&PuzzleInput::new(TEST_INPUT)).unwrap(), 1651);
     }
 
     #[test]
     fn test_solve_b() {
-        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)), 1707);
+        assert_eq!(solve_b(&PuzzleInput::new(TEST_INPUT)).unwrap(), 1707);
+    }
+
+    #[test]
+    fn test_large_flow_rate() {
+        let input = PuzzleInput::new(
+            "Valve AA has flow rate=0; tunnels lead to valves BB
+Valve BB has flow rate=1000000000000000000; tunnels lead to valves AA",
+        );
+        assert!(solve_a(&input).is_err());
+        assert!(solve_b(&input).is_err());
+
+        let input = PuzzleInput::new(
+            "Valve AA has flow rate=0; tunnels lead to valves BB
+Valve BB has flow rate=4294967295; tunnels lead to valves AA",
+        );
+        assert_eq!(solve_a(&input).unwrap(), 28 * 4294967295);
+        assert_eq!(solve_b(&input).unwrap(), 24 * 4294967295);
+    }
+
+    #[test]
+    fn test_smoke() {
+        aoc_utils::smoke::smoke_test(DAY, &[TEST_INPUT], solve_a, solve_b);
     }
 }
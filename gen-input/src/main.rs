@@ -0,0 +1,41 @@
+use std::io::{self, Write};
+
+use aoc_utils::check::StdRng;
+use aoc_utils::generate;
+use rand::SeedableRng;
+
+// This is a utility that generates a random puzzle input for a day and writes it to stdout.
+// Usage: cargo run -p gen-input -- <day> [size] [seed]
+// The size is the number of the day's main items (elves, moves, files, ...), see aoc_utils::generate.
+// The generated input can be solved by pointing the AOC_INPUT env variable to it, e.g.:
+// cargo run -p gen-input -- 1 100000 > big.txt && AOC_INPUT=big.txt cargo run --release -p day01
+
+const DEFAULT_SIZE: usize = 1000;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.len() > 3 {
+        eprintln!("Usage: gen-input <day> [size] [seed]");
+        std::process::exit(2);
+    }
+
+    let day = parse_arg::<u8>(&args[0], "day");
+    let size = args.get(1).map_or(DEFAULT_SIZE, |s| parse_arg(s, "size"));
+    let seed = args.get(2).map_or(0, |s| parse_arg(s, "seed"));
+
+    let generator = generate::for_day(day).unwrap_or_else(|| {
+        eprintln!("No input generator for day {}", day);
+        std::process::exit(2);
+    });
+
+    let input = generator(&mut StdRng::seed_from_u64(seed), size);
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", input).expect("Could not write input");
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: &str, name: &str) -> T {
+    arg.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid {}: {}", name, arg);
+        std::process::exit(2);
+    })
+}